@@ -0,0 +1,192 @@
+//! A multiset that counts how often each key was added.
+
+use std::collections::hash_map;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::hash::Hash;
+
+/// An unsigned integer type a [`Counter`] can count with.
+pub trait Count: Copy + Ord + Default + Debug {
+    const ONE: Self;
+
+    fn checked_add(self, other: Self) -> Option<Self>;
+}
+
+macro_rules! impl_count {
+    ($($t:ty),*) => {$(
+        impl Count for $t {
+            const ONE: Self = 1;
+
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+        }
+    )*};
+}
+
+impl_count!(u8, u16, u32, u64, u128, usize);
+
+/// How many times each key has been seen. Only keys with a non-zero count are stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Counter<K: Hash + Eq, N: Count = usize> {
+    counts: HashMap<K, N>,
+}
+
+impl<K: Hash + Eq, N: Count> Counter<K, N> {
+    pub fn new() -> Counter<K, N> {
+        Counter {
+            counts: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, key: K) {
+        self.add_n(key, N::ONE)
+    }
+
+    /// Adds `n` to `key`'s count. Panics if the count overflows; see [`Counter::try_add_n`].
+    pub fn add_n(&mut self, key: K, n: N) {
+        self.try_add_n(key, n).expect("Counter overflowed")
+    }
+
+    /// Adds `n` to `key`'s count, or returns `None` (leaving the count as it was) on overflow.
+    pub fn try_add_n(&mut self, key: K, n: N) -> Option<()> {
+        if n == N::default() {
+            return Some(());
+        }
+        let count = self.counts.entry(key).or_default();
+        *count = count.checked_add(n)?;
+        Some(())
+    }
+
+    /// Adds every count in `other` to this counter.
+    pub fn merge(&mut self, other: Counter<K, N>) {
+        for (key, n) in other.counts {
+            self.add_n(key, n);
+        }
+    }
+
+    /// `key`'s count, which is zero for keys that were never added.
+    pub fn get(&self, key: &K) -> N {
+        self.counts.get(key).copied().unwrap_or_default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.counts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+    }
+
+    pub fn keys(&self) -> hash_map::Keys<'_, K, N> {
+        self.counts.keys()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, N)> {
+        self.counts.iter().map(|(key, &n)| (key, n))
+    }
+
+    pub fn max_count(&self) -> Option<N> {
+        self.counts.values().max().copied()
+    }
+
+    pub fn min_count(&self) -> Option<N> {
+        self.counts.values().min().copied()
+    }
+}
+
+impl<K: Hash + Eq + Ord, N: Count> Counter<K, N> {
+    /// The key with the highest count, the smallest key winning ties.
+    pub fn most_common(&self) -> Option<(&K, N)> {
+        self.sorted_by_count().into_iter().next()
+    }
+
+    /// The key with the lowest count, the smallest key winning ties.
+    pub fn least_common(&self) -> Option<(&K, N)> {
+        self.iter()
+            .min_by(|(k1, n1), (k2, n2)| n1.cmp(n2).then_with(|| k1.cmp(k2)))
+    }
+
+    /// All keys and counts, highest count first, then by key.
+    pub fn sorted_by_count(&self) -> Vec<(&K, N)> {
+        let mut sorted: Vec<(&K, N)> = self.iter().collect();
+        sorted.sort_by(|(k1, n1), (k2, n2)| n2.cmp(n1).then_with(|| k1.cmp(k2)));
+        sorted
+    }
+}
+
+impl<K: Hash + Eq, N: Count> Default for Counter<K, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq, N: Count> Extend<K> for Counter<K, N> {
+    fn extend<I: IntoIterator<Item = K>>(&mut self, iter: I) {
+        for key in iter {
+            self.add(key);
+        }
+    }
+}
+
+impl<K: Hash + Eq, N: Count> FromIterator<K> for Counter<K, N> {
+    fn from_iter<I: IntoIterator<Item = K>>(iter: I) -> Self {
+        let mut counter = Counter::new();
+        counter.extend(iter);
+        counter
+    }
+}
+
+impl<K: Hash + Eq, N: Count> IntoIterator for Counter<K, N> {
+    type Item = (K, N);
+    type IntoIter = hash_map::IntoIter<K, N>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.counts.into_iter()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::counter::Counter;
+
+    #[test]
+    fn test_counts() {
+        let mut counter: Counter<char> = "NBCCNBBBCBHCB".chars().collect();
+        counter.add_n('H', 2);
+        counter.add_n('Z', 0);
+
+        assert_eq!(6, counter.get(&'B'));
+        assert_eq!(0, counter.get(&'Z'));
+        assert_eq!(4, counter.len());
+        assert_eq!(Some((&'B', 6)), counter.most_common());
+        assert_eq!(Some((&'N', 2)), counter.least_common());
+        assert_eq!(
+            (Some(2), Some(6)),
+            (counter.min_count(), counter.max_count())
+        );
+        assert_eq!(
+            vec![(&'B', 6), (&'C', 4), (&'H', 3), (&'N', 2)],
+            counter.sorted_by_count()
+        );
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut counter: Counter<&str, u64> = ["a", "b", "a"].into_iter().collect();
+        counter.merge(["b", "c"].into_iter().collect());
+        assert_eq!(
+            vec![(&"a", 2), (&"b", 2), (&"c", 1)],
+            counter.sorted_by_count()
+        );
+    }
+
+    #[test]
+    fn test_overflow() {
+        let mut counter: Counter<char, u8> = Counter::new();
+        counter.add_n('a', 250);
+        assert_eq!(None, counter.try_add_n('a', 6));
+        assert_eq!(250, counter.get(&'a'));
+        assert_eq!(Some(()), counter.try_add_n('a', 5));
+    }
+}
@@ -1,40 +1,219 @@
+use crate::days::parse::{ParseResult, Span};
 use crate::days::template::Solution;
+use std::collections::VecDeque;
+use std::io::{self, BufRead};
 
 pub struct Day01 {}
 
 impl Solution for Day01 {
     fn part1(&self, input: &str) -> String {
-        let values = parse_values(input);
-        values
-            .iter()
-            .zip(values[1..].iter())
-            .fold(0, |r, (prev, cur)| if cur > prev { r + 1 } else { r })
+        analyze_lines(1, input.lines())
+            .expect("Invalid input")
+            .increases
             .to_string()
     }
 
     fn part2(&self, input: &str) -> String {
-        let values = parse_values(input);
-        let first_triples = values.windows(3);
-        let second_triples = values[1..].windows(3);
-
-        first_triples
-            .into_iter()
-            .zip(second_triples.into_iter())
-            .fold(0, |r, (prev, cur)| {
-                if cur.iter().sum::<i32>() > prev.iter().sum::<i32>() {
-                    r + 1
-                } else {
-                    r
-                }
-            })
+        analyze_lines(3, input.lines())
+            .expect("Invalid input")
+            .increases
             .to_string()
     }
 }
 
-fn parse_values(input: &str) -> Vec<i32> {
-    input
-        .trim()
-        .split_whitespace()
-        .map(|it| it.parse::<i32>().unwrap())
-        .collect()
+/// How the sums of each `window` consecutive depths changed along a sonar sweep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SonarReport {
+    pub window: usize,
+    /// How many window sums were larger than the one before.
+    pub increases: usize,
+    /// The 0-based index of the depth ending each window whose sum was larger than the one before.
+    pub increase_positions: Vec<usize>,
+    /// The most increases in a row.
+    pub longest_increasing_run: usize,
+    /// The largest rise from one window sum to the next, or `None` if no sum rose.
+    pub largest_increase: Option<i128>,
+}
+
+/// Compares window sums one depth at a time, holding on to just the last `window` depths.
+#[derive(Debug, Clone)]
+pub struct SonarAnalyzer {
+    recent: VecDeque<i64>,
+    depths_seen: usize,
+    current_run: usize,
+    report: SonarReport,
+}
+
+impl SonarAnalyzer {
+    pub fn new(window: usize) -> SonarAnalyzer {
+        assert!(window > 0, "Window size must be at least 1");
+        SonarAnalyzer {
+            recent: VecDeque::with_capacity(window + 1),
+            depths_seen: 0,
+            current_run: 0,
+            report: SonarReport {
+                window,
+                increases: 0,
+                increase_positions: Vec::new(),
+                longest_increasing_run: 0,
+                largest_increase: None,
+            },
+        }
+    }
+
+    pub fn push(&mut self, depth: i64) {
+        let position = self.depths_seen;
+        self.depths_seen += 1;
+        self.recent.push_back(depth);
+        if self.recent.len() <= self.report.window {
+            return;
+        }
+
+        // consecutive windows share all but their ends, so the sum changes by the depth that
+        // joined minus the one that left
+        let left = self.recent.pop_front().unwrap();
+        let change = depth as i128 - left as i128;
+        if change > 0 {
+            let report = &mut self.report;
+            report.increases += 1;
+            report.increase_positions.push(position);
+            report.largest_increase = report.largest_increase.max(Some(change));
+            self.current_run += 1;
+            report.longest_increasing_run = report.longest_increasing_run.max(self.current_run);
+        } else {
+            self.current_run = 0;
+        }
+    }
+
+    pub fn report(&self) -> &SonarReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> SonarReport {
+        self.report
+    }
+}
+
+/// Analyzes a sweep given one depth per line, e.g. from `str::lines`. Blank lines are skipped;
+/// anything else that isn't an integer is an error naming its line.
+pub fn analyze_lines<S: AsRef<str>>(
+    window: usize,
+    lines: impl IntoIterator<Item = S>,
+) -> ParseResult<SonarReport> {
+    analyze(window, lines.into_iter().map(Ok))
+}
+
+/// [`analyze_lines`] reading from `reader` a line at a time, so the sweep never has to fit in
+/// memory. A line that can't be read is an error naming it too.
+pub fn analyze_reader(window: usize, reader: impl BufRead) -> ParseResult<SonarReport> {
+    analyze(window, reader.lines())
+}
+
+fn analyze<S: AsRef<str>>(
+    window: usize,
+    lines: impl Iterator<Item = io::Result<S>>,
+) -> ParseResult<SonarReport> {
+    let mut analyzer = SonarAnalyzer::new(window);
+    for (index, line) in lines.enumerate() {
+        let at = Span {
+            text: "",
+            line: index + 1,
+            column: 1,
+        };
+        let line = line.map_err(|e| at.error(format!("cannot read line: {}", e)))?;
+        let span = Span {
+            text: line.as_ref(),
+            ..at
+        }
+        .trim();
+        if !span.is_empty() {
+            analyzer.push(span.parse()?);
+        }
+    }
+    Ok(analyzer.into_report())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::day01::{analyze_lines, analyze_reader, Day01, SonarAnalyzer};
+    use crate::Solution;
+    use std::io::Cursor;
+
+    const SAMPLE_1: &str = "199
+200
+208
+210
+200
+207
+240
+269
+260
+263";
+
+    #[test]
+    fn test_sample_part1() {
+        assert_eq!(7.to_string(), Day01 {}.part1(SAMPLE_1));
+    }
+
+    #[test]
+    fn test_sample_part2() {
+        assert_eq!(5.to_string(), Day01 {}.part2(SAMPLE_1));
+    }
+
+    #[test]
+    fn test_windows_that_overflow_i64() {
+        let max = i64::MAX;
+        let input = format!("{}\n{}\n{}\n{}\n{}", max - 2, max - 1, max - 1, max, max);
+        assert_eq!(2.to_string(), Day01 {}.part2(&input));
+    }
+
+    #[test]
+    fn test_sample_report() {
+        let report = analyze_lines(1, SAMPLE_1.lines()).unwrap();
+        assert_eq!(vec![1, 2, 3, 5, 6, 7, 9], report.increase_positions);
+        assert_eq!(3, report.longest_increasing_run);
+        assert_eq!(Some(33), report.largest_increase);
+
+        let report = analyze_lines(3, SAMPLE_1.lines()).unwrap();
+        // sums 599, 618, 618, 617, 647, 716, 769, 792
+        assert_eq!(vec![3, 6, 7, 8, 9], report.increase_positions);
+        assert_eq!(4, report.longest_increasing_run);
+        assert_eq!(Some(69), report.largest_increase);
+    }
+
+    #[test]
+    fn test_windows_longer_than_input() {
+        let report = analyze_lines(20, SAMPLE_1.lines()).unwrap();
+        assert_eq!(0, report.increases);
+        assert_eq!(None, report.largest_increase);
+    }
+
+    #[test]
+    fn test_streams_without_collecting() {
+        let mut analyzer = SonarAnalyzer::new(5);
+        (0..1_000_000).for_each(|depth| analyzer.push(depth % 1000));
+        // every window rises except the 5 falls at each of the 999 wraps back round to 0
+        assert_eq!(1_000_000 - 5 - 999 * 5, analyzer.report().increases);
+        assert_eq!(Some(5), analyzer.report().largest_increase);
+    }
+
+    #[test]
+    fn test_bad_line_is_reported() {
+        let error = analyze_lines(1, "199\n\n200\n2o8".lines()).unwrap_err();
+        assert_eq!(4, error.line);
+    }
+
+    #[test]
+    fn test_reader() {
+        let report = analyze_reader(3, Cursor::new(SAMPLE_1)).unwrap();
+        assert_eq!(analyze_lines(3, SAMPLE_1.lines()).unwrap(), report);
+
+        let error = analyze_reader(1, Cursor::new("199\n\n200\n2o8\n")).unwrap_err();
+        assert_eq!(4, error.line);
+
+        // the third line isn't valid UTF-8
+        let error = analyze_reader(1, Cursor::new(b"199\n200\n\xff\n".as_slice())).unwrap_err();
+        assert_eq!(3, error.line);
+        assert!(error.message.starts_with("cannot read line"));
+    }
 }
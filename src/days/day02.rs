@@ -1,51 +1,370 @@
+use crate::days::parse::{ParseResult, Span};
 use crate::days::template::Solution;
-use itertools::Itertools;
+use std::fmt;
+use std::io::{self, Write};
 
 pub struct Day02 {}
 
 impl Solution for Day02 {
     fn part1(&self, input: &str) -> String {
-        let pos: (i32, i32) = parse_values(input)
-            .iter()
-            .fold((0, 0), |(x, y), (dir, delta)| match dir {
-                Direction::Up => (x, y - delta),
-                Direction::Down => (x, y + delta),
-                Direction::Forward => (x + delta, y),
-            });
-        (pos.0 * pos.1).to_string()
+        Self::final_product(input, MovementModel::Plain)
     }
 
     fn part2(&self, input: &str) -> String {
-        let pos: (i32, i32, i32) =
-            parse_values(input)
-                .iter()
-                .fold((0, 0, 0), |(x, y, aim), (dir, delta)| match dir {
-                    Direction::Up => (x, y, aim - delta),
-                    Direction::Down => (x, y, aim + delta),
-                    Direction::Forward => (x + delta, y + aim * delta, aim),
-                });
-        (pos.0 * pos.1).to_string()
+        Self::final_product(input, MovementModel::Aim)
     }
 }
 
-fn parse_values(input: &str) -> Vec<(Direction, i32)> {
-    input
+impl Day02 {
+    fn final_product(input: &str, model: MovementModel) -> String {
+        let commands = parse_commands(input).expect("Invalid input");
+        let end = Course::plot(model, &commands).final_position();
+        (end.horizontal * end.depth).to_string()
+    }
+}
+
+/// Reads one `<direction> <distance>` command per line, rejecting any direction other than
+/// `forward`, `down` or `up`.
+pub fn parse_commands(input: &str) -> ParseResult<Vec<Command>> {
+    Span::new(input)
         .trim()
         .lines()
-        .map(|s| s.split_whitespace().into_iter().next_tuple().unwrap())
-        .map(|(dir_str, num)| {
-            let dir = match dir_str {
-                "up" => Direction::Up,
+        .map(|line| {
+            let (dir_str, num) = line
+                .trim()
+                .split_once(" ")
+                .ok_or_else(|| line.error("expected `<direction> <distance>`"))?;
+            let direction = match dir_str.text {
+                "forward" => Direction::Forward,
                 "down" => Direction::Down,
-                _ => Direction::Forward,
+                "up" => Direction::Up,
+                unknown => return Err(dir_str.error(format!("unknown command `{}`", unknown))),
             };
-            (dir, num.parse::<i32>().unwrap())
+            Ok(Command {
+                direction,
+                distance: num.trim().parse()?,
+            })
         })
         .collect()
 }
 
-enum Direction {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
     Up,
     Down,
     Forward,
 }
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Command {
+    pub direction: Direction,
+    pub distance: i64,
+}
+
+impl Command {
+    fn forward(distance: i64) -> Command {
+        Command {
+            direction: Direction::Forward,
+            distance,
+        }
+    }
+
+    /// `down` for a positive change, `up` for a negative one.
+    fn vertical(change: i64) -> Command {
+        Command {
+            direction: if change > 0 {
+                Direction::Down
+            } else {
+                Direction::Up
+            },
+            distance: change.abs(),
+        }
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let verb = match self.direction {
+            Direction::Up => "up",
+            Direction::Down => "down",
+            Direction::Forward => "forward",
+        };
+        write!(f, "{} {}", verb, self.distance)
+    }
+}
+
+/// How commands move the submarine.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MovementModel {
+    /// `up` and `down` change the depth directly (part 1).
+    Plain,
+    /// `up` and `down` change the aim, and `forward` also dives by aim times distance (part 2).
+    Aim,
+}
+
+impl MovementModel {
+    pub fn apply(self, from: Position, command: &Command) -> Position {
+        let Position {
+            horizontal,
+            depth,
+            aim,
+        } = from;
+        let delta = command.distance;
+        match (self, command.direction) {
+            (MovementModel::Plain, Direction::Up) => Position {
+                depth: depth - delta,
+                ..from
+            },
+            (MovementModel::Plain, Direction::Down) => Position {
+                depth: depth + delta,
+                ..from
+            },
+            (MovementModel::Plain, Direction::Forward) => Position {
+                horizontal: horizontal + delta,
+                ..from
+            },
+            (MovementModel::Aim, Direction::Up) => Position {
+                aim: aim - delta,
+                ..from
+            },
+            (MovementModel::Aim, Direction::Down) => Position {
+                aim: aim + delta,
+                ..from
+            },
+            (MovementModel::Aim, Direction::Forward) => Position {
+                horizontal: horizontal + delta,
+                depth: depth + aim * delta,
+                aim,
+            },
+        }
+    }
+}
+
+/// A shortest list of commands, each with a positive distance, that takes the submarine from the
+/// surface to `horizontal`, `depth` under `model`. `None` if there isn't one: the submarine can't
+/// go backwards, and under the aim model it can't change depth without moving forward.
+pub fn plan_course(model: MovementModel, horizontal: i64, depth: i64) -> Option<Vec<Command>> {
+    if horizontal < 0 || (model == MovementModel::Aim && horizontal == 0 && depth != 0) {
+        return None;
+    }
+
+    let mut commands = Vec::new();
+    match model {
+        MovementModel::Plain => {
+            commands.push(Command::forward(horizontal));
+            commands.push(Command::vertical(depth));
+        }
+        MovementModel::Aim if depth % horizontal.max(1) == 0 => {
+            // one dive at a steady aim
+            commands.push(Command::vertical(depth / horizontal.max(1)));
+            commands.push(Command::forward(horizontal));
+        }
+        MovementModel::Aim => {
+            // cruise at the surface, then take the whole depth in a single step forward
+            commands.push(Command::forward(horizontal - 1));
+            commands.push(Command::vertical(depth));
+            commands.push(Command::forward(1));
+        }
+    }
+    commands.retain(|command| command.distance != 0);
+    Some(commands)
+}
+
+/// A shortest list of commands that reaches `horizontal`, `depth` under both movement models at
+/// once: the aim it ends with has to be the depth, and only a final step of 1 dives by the aim.
+pub fn plan_course_for_both(horizontal: i64, depth: i64) -> Option<Vec<Command>> {
+    if horizontal < 0 || (horizontal == 0 && depth != 0) {
+        return None;
+    }
+
+    let mut commands = if depth == 0 {
+        vec![Command::forward(horizontal)]
+    } else {
+        vec![
+            Command::forward(horizontal - 1),
+            Command::vertical(depth),
+            Command::forward(1),
+        ]
+    };
+    commands.retain(|command| command.distance != 0);
+    Some(commands)
+}
+
+/// Where the submarine is. `aim` stays 0 under [`MovementModel::Plain`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Position {
+    pub horizontal: i64,
+    pub depth: i64,
+    pub aim: i64,
+}
+
+/// Every position the submarine passes through, starting at the surface.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Course {
+    pub positions: Vec<Position>,
+}
+
+impl Course {
+    pub fn plot(model: MovementModel, commands: &[Command]) -> Course {
+        let mut positions = vec![Position::default()];
+        for command in commands {
+            let last = *positions.last().unwrap();
+            positions.push(model.apply(last, command));
+        }
+        Course { positions }
+    }
+
+    pub fn final_position(&self) -> Position {
+        *self.positions.last().unwrap()
+    }
+
+    pub fn max_depth(&self) -> i64 {
+        self.positions.iter().map(|p| p.depth).max().unwrap()
+    }
+
+    /// How far the submarine travelled: the horizontal plus vertical distance of every move.
+    pub fn total_distance(&self) -> i64 {
+        self.positions
+            .windows(2)
+            .map(|pair| {
+                (pair[1].horizontal - pair[0].horizontal).abs()
+                    + (pair[1].depth - pair[0].depth).abs()
+            })
+            .sum()
+    }
+
+    /// Writes a `step,horizontal,depth,aim` header, then a row per position.
+    pub fn write_csv(&self, mut out: impl Write) -> io::Result<()> {
+        writeln!(out, "step,horizontal,depth,aim")?;
+        for (step, p) in self.positions.iter().enumerate() {
+            writeln!(out, "{},{},{},{}", step, p.horizontal, p.depth, p.aim)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::day02::{
+        parse_commands, plan_course, plan_course_for_both, Command, Course, Day02, MovementModel,
+    };
+    use crate::Solution;
+    use itertools::Itertools;
+
+    const SAMPLE_1: &str = "forward 5
+down 5
+forward 8
+up 3
+down 8
+forward 2";
+
+    #[test]
+    fn test_sample_part1() {
+        assert_eq!(150.to_string(), Day02 {}.part1(SAMPLE_1));
+    }
+
+    #[test]
+    fn test_sample_part2() {
+        assert_eq!(900.to_string(), Day02 {}.part2(SAMPLE_1));
+    }
+
+    #[test]
+    fn test_unknown_command_is_rejected() {
+        let error = parse_commands("forward 5\ndown 5\nfroward 8").unwrap_err();
+        assert_eq!((3, 1), (error.line, error.column));
+        assert!(error.message.contains("froward"));
+    }
+
+    #[test]
+    fn test_sample_courses() {
+        let commands = parse_commands(SAMPLE_1).unwrap();
+
+        let plain = Course::plot(MovementModel::Plain, &commands);
+        let depths: Vec<i64> = plain.positions.iter().map(|p| p.depth).collect();
+        assert_eq!(vec![0, 0, 5, 5, 2, 10, 10], depths);
+        assert_eq!(10, plain.max_depth());
+        assert_eq!(31, plain.total_distance());
+
+        let aim = Course::plot(MovementModel::Aim, &commands);
+        assert_eq!(60, aim.max_depth());
+        // forward 8 at aim 5 dives 40, forward 2 at aim 10 dives 20
+        assert_eq!(15 + 40 + 20, aim.total_distance());
+    }
+
+    #[test]
+    fn test_write_csv() {
+        let commands = parse_commands("forward 5\ndown 5\nforward 8").unwrap();
+        let mut csv = Vec::new();
+        Course::plot(MovementModel::Aim, &commands)
+            .write_csv(&mut csv)
+            .unwrap();
+        assert_eq!(
+            "step,horizontal,depth,aim\n0,0,0,0\n1,5,0,0\n2,5,0,5\n3,13,40,5\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+
+    fn to_input(commands: &[Command]) -> String {
+        commands.iter().join("\n")
+    }
+
+    /// Checks that `commands` end at `horizontal`, `depth` under `model`, and that the day's
+    /// answer for them is the product.
+    fn assert_reaches(model: MovementModel, commands: &[Command], horizontal: i64, depth: i64) {
+        let end = Course::plot(model, commands).final_position();
+        assert_eq!(
+            (horizontal, depth),
+            (end.horizontal, end.depth),
+            "{:?}",
+            commands
+        );
+        if commands.is_empty() {
+            return;
+        }
+        let answer = match model {
+            MovementModel::Plain => Day02 {}.part1(&to_input(commands)),
+            MovementModel::Aim => Day02 {}.part2(&to_input(commands)),
+        };
+        assert_eq!((horizontal * depth).to_string(), answer);
+    }
+
+    #[test]
+    fn test_planned_courses_reach_their_targets() {
+        for horizontal in 0..8 {
+            for depth in -8..8 {
+                let plain = plan_course(MovementModel::Plain, horizontal, depth).unwrap();
+                assert_reaches(MovementModel::Plain, &plain, horizontal, depth);
+
+                let both = plan_course_for_both(horizontal, depth);
+                let aim = plan_course(MovementModel::Aim, horizontal, depth);
+                if horizontal == 0 && depth != 0 {
+                    assert_eq!((None, None), (both, aim));
+                    continue;
+                }
+                assert_reaches(MovementModel::Aim, &aim.unwrap(), horizontal, depth);
+                let both = both.unwrap();
+                assert_reaches(MovementModel::Plain, &both, horizontal, depth);
+                assert_reaches(MovementModel::Aim, &both, horizontal, depth);
+            }
+        }
+    }
+
+    #[test]
+    fn test_planned_course_lengths() {
+        let lengths = |horizontal, depth| {
+            (
+                plan_course(MovementModel::Plain, horizontal, depth).map(|c| c.len()),
+                plan_course(MovementModel::Aim, horizontal, depth).map(|c| c.len()),
+                plan_course_for_both(horizontal, depth).map(|c| c.len()),
+            )
+        };
+        assert_eq!((Some(0), Some(0), Some(0)), lengths(0, 0));
+        assert_eq!((Some(1), Some(1), Some(1)), lengths(15, 0));
+        assert_eq!((Some(2), Some(2), Some(2)), lengths(1, -4));
+        // 60 is a multiple of 15, so aiming at 4 for the whole way works
+        assert_eq!((Some(2), Some(2), Some(3)), lengths(15, 60));
+        assert_eq!((Some(2), Some(3), Some(3)), lengths(15, 61));
+        assert_eq!((Some(1), None, None), lengths(0, 10));
+        assert_eq!((None, None, None), lengths(-1, 0));
+    }
+}
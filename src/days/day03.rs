@@ -1,103 +1,234 @@
+use crate::days::parse::{ParseResult, Span};
 use crate::days::template::Solution;
-use itertools::FoldWhile::{Continue, Done};
-use itertools::Itertools;
 
 pub struct Day03 {}
 
 impl Solution for Day03 {
     fn part1(&self, input: &str) -> String {
-        let values = parse_values(input);
-        let count_zeroes_ones: Vec<(usize, usize)> = zeroes_and_ones_by_bit(&values);
+        let report = DiagnosticReport::parse(input).expect("Invalid input");
+        report
+            .power_consumption()
+            .expect("Power consumption doesn't fit in a u128")
+            .to_string()
+    }
 
-        let gamma_bin = count_zeroes_ones
-            .iter()
-            .map(|&counts| if counts.1 > counts.0 { '1' } else { '0' })
-            .join("");
-        let gamma = usize::from_str_radix(&gamma_bin, 2).unwrap();
+    fn part2(&self, input: &str) -> String {
+        let report = DiagnosticReport::parse(input).expect("Invalid input");
+        report
+            .life_support_rating()
+            .expect("No life support rating")
+            .to_string()
+    }
+}
 
-        let epsilon_bin = gamma_bin
-            .chars()
-            .map(|ch| if ch == '1' { '0' } else { '1' })
-            .join("");
-        let epsilon = usize::from_str_radix(&epsilon_bin, 2).unwrap();
+/// A diagnostic report: binary numbers that are all `width` bits wide, up to 128.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagnosticReport {
+    width: u32,
+    /// In ascending order, so the numbers sharing any run of leading bits sit next to each other.
+    values: Vec<u128>,
+}
 
-        (gamma * epsilon).to_string()
+impl DiagnosticReport {
+    pub fn new(width: u32, mut values: Vec<u128>) -> DiagnosticReport {
+        assert!(
+            (1..=u128::BITS).contains(&width),
+            "Width must be from 1 to 128 bits"
+        );
+        assert!(
+            values
+                .iter()
+                .all(|&v| width == u128::BITS || v >> width == 0),
+            "Every value must fit in {} bits",
+            width
+        );
+        values.sort_unstable();
+        DiagnosticReport { width, values }
     }
 
-    fn part2(&self, input: &str) -> String {
-        let parsed_values = parse_values(input);
-        let value_len = parsed_values[0].len();
-
-        let reduce_by_bit = |values: &mut Vec<&str>, invert: bool, acc, i: usize| {
-            let most_common = most_common_char_for_bit(values, i);
-            values.retain(|v| {
-                let nth_char = v.chars().nth(i);
-                let is_most_common = nth_char == Some(most_common);
-                if invert {
-                    !is_most_common
-                } else {
-                    is_most_common
+    /// Reads one binary number per line; every line must have the same number of digits.
+    pub fn parse(input: &str) -> ParseResult<DiagnosticReport> {
+        let input = Span::new(input).trim();
+        let mut width = None;
+        let mut values = Vec::new();
+        for line in input.lines() {
+            let line = line.trim();
+            if line.is_empty() || !line.text.chars().all(|ch| ch == '0' || ch == '1') {
+                return Err(line.error(format!("expected a binary number, found `{}`", line.text)));
+            }
+            if line.text.len() > u128::BITS as usize {
+                return Err(line.error("expected at most 128 bits"));
+            }
+
+            let line_width = line.text.len() as u32;
+            match width {
+                None => width = Some(line_width),
+                Some(w) if w != line_width => {
+                    return Err(line.error(format!("expected {} bits, found {}", w, line_width)))
                 }
-            });
+                Some(_) => {}
+            }
+            values.push(u128::from_str_radix(line.text, 2).unwrap());
+        }
 
-            if values.len() != 1 {
-                Continue(acc)
-            } else {
-                Done(values[0].to_string())
+        Ok(DiagnosticReport::new(width.unwrap(), values))
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// The report's numbers, in ascending order.
+    pub fn values(&self) -> &[u128] {
+        &self.values
+    }
+
+    /// How many numbers have each bit set, least significant bit first.
+    pub fn ones_by_bit(&self) -> Vec<usize> {
+        let mut ones = vec![0; self.width as usize];
+        for &value in &self.values {
+            // only visit the bits that are set
+            let mut remaining = value;
+            while remaining != 0 {
+                ones[remaining.trailing_zeros() as usize] += 1;
+                remaining &= remaining - 1;
             }
-        };
-
-        let mut o2_values = parsed_values.to_vec();
-        let o2_str = (0..value_len)
-            .fold_while(String::new(), |acc, i| {
-                reduce_by_bit(&mut o2_values, false, acc, i)
-            })
-            .into_inner();
-        let o2_val = usize::from_str_radix(&o2_str, 2).unwrap();
-
-        let mut co2_values = parsed_values.to_vec();
-        let co2_str = (0..value_len)
-            .fold_while(String::new(), |acc, i| {
-                reduce_by_bit(&mut co2_values, true, acc, i)
-            })
-            .into_inner();
-        let co2_val = usize::from_str_radix(&co2_str, 2).unwrap();
-
-        (o2_val * co2_val).to_string()
+        }
+        ones
     }
-}
 
-fn zeroes_and_ones_by_bit(values: &[&str]) -> Vec<(usize, usize)> {
-    (0..values[0].len())
-        .into_iter()
-        .map(|digit_i| {
-            // concatenate i-th digit of each line to produce value_len strings of values.len()
-            // length
-            values.iter().fold(String::new(), |vertical_str, value| {
-                let ch = value.chars().nth(digit_i).unwrap();
-                format!("{}{}", vertical_str, ch)
-            })
-        })
-        .map(|vertical_str| {
-            let ones = vertical_str.chars().filter(|&ch| ch == '1').count();
-            (vertical_str.len() - ones, ones)
-        })
-        .collect()
-}
+    /// Each bit set if more than half the numbers have it set.
+    pub fn gamma_rate(&self) -> u128 {
+        self.ones_by_bit()
+            .iter()
+            .enumerate()
+            .filter(|&(_, &ones)| ones > self.values.len() - ones)
+            .fold(0, |gamma, (bit, _)| gamma | 1 << bit)
+    }
 
-fn most_common_char_for_bit(values: &[&str], i: usize) -> char {
-    let count_zeroes_ones: (usize, usize) = zeroes_and_ones_single_bit(values, i);
-    if count_zeroes_ones.1 >= count_zeroes_ones.0 {
-        '1'
-    } else {
-        '0'
+    /// The gamma rate with every bit flipped.
+    pub fn epsilon_rate(&self) -> u128 {
+        !self.gamma_rate() & self.mask()
+    }
+
+    pub fn power_consumption(&self) -> Option<u128> {
+        self.gamma_rate().checked_mul(self.epsilon_rate())
     }
-}
 
-fn zeroes_and_ones_single_bit(values: &[&str], index: usize) -> (usize, usize) {
-    *zeroes_and_ones_by_bit(values).get(index).unwrap()
+    /// The number left after keeping only those with the most common value of each bit in turn,
+    /// from the most significant, with ties going to 1. `None` for an empty report.
+    pub fn oxygen_generator_rating(&self) -> Option<u128> {
+        self.rating(|zeroes, ones| ones >= zeroes)
+    }
+
+    /// Like the oxygen generator rating, but keeping the least common value, with ties going to 0.
+    pub fn co2_scrubber_rating(&self) -> Option<u128> {
+        self.rating(|zeroes, ones| ones < zeroes)
+    }
+
+    pub fn life_support_rating(&self) -> Option<u128> {
+        self.oxygen_generator_rating()?
+            .checked_mul(self.co2_scrubber_rating()?)
+    }
+
+    /// Narrows the sorted numbers down one bit at a time, keeping those with the bit set when
+    /// `keep_ones(zeroes, ones)` holds. Within a run sharing the bits already looked at, the
+    /// numbers without the next bit come first, so each step is a binary search.
+    fn rating(&self, keep_ones: impl Fn(usize, usize) -> bool) -> Option<u128> {
+        let mut remaining = &self.values[..];
+        for bit in (0..self.width).rev() {
+            if remaining.len() <= 1 {
+                break;
+            }
+            let (zeroes, ones) =
+                remaining.split_at(remaining.partition_point(|v| v >> bit & 1 == 0));
+            remaining = if ones.is_empty()
+                || (!zeroes.is_empty() && !keep_ones(zeroes.len(), ones.len()))
+            {
+                zeroes
+            } else {
+                ones
+            };
+        }
+        remaining.first().copied()
+    }
+
+    fn mask(&self) -> u128 {
+        u128::MAX >> (u128::BITS - self.width)
+    }
 }
 
-fn parse_values(input: &str) -> Vec<&str> {
-    input.trim().lines().collect()
+#[cfg(test)]
+mod tests {
+    use crate::days::day03::{Day03, DiagnosticReport};
+    use crate::Solution;
+
+    const SAMPLE_1: &str = "00100
+11110
+10110
+10111
+10101
+01111
+00111
+11100
+10000
+11001
+00010
+01010";
+
+    #[test]
+    fn test_sample_part1() {
+        assert_eq!(198.to_string(), Day03 {}.part1(SAMPLE_1));
+    }
+
+    #[test]
+    fn test_sample_part2() {
+        assert_eq!(230.to_string(), Day03 {}.part2(SAMPLE_1));
+    }
+
+    #[test]
+    fn test_sample_ratings() {
+        let report = DiagnosticReport::parse(SAMPLE_1).unwrap();
+        assert_eq!(5, report.width());
+        assert_eq!(vec![5, 7, 8, 5, 7], report.ones_by_bit());
+        assert_eq!(22, report.gamma_rate());
+        assert_eq!(9, report.epsilon_rate());
+        assert_eq!(Some(23), report.oxygen_generator_rating());
+        assert_eq!(Some(10), report.co2_scrubber_rating());
+    }
+
+    #[test]
+    fn test_128_bit_values() {
+        let high = format!("1{}", "0".repeat(127));
+        let low = format!("0{}", "1".repeat(127));
+        let report = DiagnosticReport::parse(&format!("{}\n{}\n{}", high, high, low)).unwrap();
+        assert_eq!(1 << 127, report.gamma_rate());
+        assert_eq!(u128::MAX >> 1, report.epsilon_rate());
+        assert_eq!(None, report.power_consumption());
+        assert_eq!(Some(1 << 127), report.oxygen_generator_rating());
+        assert_eq!(Some(u128::MAX >> 1), report.co2_scrubber_rating());
+    }
+
+    #[test]
+    fn test_millions_of_lines() {
+        // every 21-bit number once, so every bit is a tie
+        let input: String = (0..1 << 21)
+            .rev()
+            .map(|v| format!("{:021b}\n", v))
+            .collect();
+        let report = DiagnosticReport::parse(&input).unwrap();
+        assert_eq!(vec![1 << 20; 21], report.ones_by_bit());
+        assert_eq!(Some(0), report.power_consumption());
+        assert_eq!(Some((1 << 21) - 1), report.oxygen_generator_rating());
+        assert_eq!(Some(0), report.co2_scrubber_rating());
+    }
+
+    #[test]
+    fn test_malformed_lines() {
+        let error = DiagnosticReport::parse("00100\n11110\n1011").unwrap_err();
+        assert_eq!(3, error.line);
+        let error = DiagnosticReport::parse("00100\n11120").unwrap_err();
+        assert_eq!(2, error.line);
+        assert!(DiagnosticReport::parse(&"1".repeat(129)).is_err());
+    }
 }
@@ -1,125 +1,351 @@
+use crate::days::parse::{self, ParseResult, Span};
 use crate::days::template::Solution;
 use itertools::Itertools;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::collections::HashMap;
 
 pub struct Day04 {}
 
 impl Solution for Day04 {
     fn part1(&self, input: &str) -> String {
-        let (num_seq, mut boards) = parse_input(input);
-
-        for num in &num_seq {
-            boards.iter_mut().for_each(|b| b.mark_value(num));
-            if let Some(winner) = boards.iter().find(|b| b.is_winner()) {
-                return (winner.sum_unmarked() * num).to_string();
-            }
+        let (numbers, boards) = parse_input(input).expect("Invalid input");
+        let ranking = rank_boards(&numbers, &boards, BingoRules::default());
+        match ranking.first().and_then(|result| result.win) {
+            Some(win) => win.score().to_string(),
+            None => String::from("No solution"),
         }
-
-        String::from("No solution")
     }
 
     fn part2(&self, input: &str) -> String {
-        let (num_seq, mut boards) = parse_input(input);
+        let (numbers, boards) = parse_input(input).expect("Invalid input");
+        let ranking = rank_boards(&numbers, &boards, BingoRules::default());
+        match ranking.iter().rev().find_map(|result| result.win) {
+            Some(win) => win.score().to_string(),
+            None => String::from("No solution"),
+        }
+    }
 
-        for num in &num_seq {
-            for b in &mut boards {
-                b.mark_value(num);
-            }
+    #[cfg(feature = "serde")]
+    fn to_json(&self, input: &str) -> Option<serde_json::Value> {
+        let (numbers, boards) = parse_input(input).expect("Invalid input");
+        Some(serde_json::json!({ "numbers": numbers, "boards": boards }))
+    }
+}
 
-            let maybe_last_winner = boards.first().cloned();
-            boards.retain(|b| !b.is_winner());
+/// The drawn numbers, then the boards. Each board is a square of numbers, one row per line, and
+/// its size is however many rows it has.
+pub fn parse_input(input: &str) -> ParseResult<(Vec<i32>, Vec<BingoBoard>)> {
+    let blocks = parse::blocks(Span::new(input));
+    let (num_block, board_blocks) = blocks
+        .split_first()
+        .ok_or_else(|| Span::new(input).error("expected a line of drawn numbers"))?;
+    let num_seq: Vec<i32> = parse::comma_separated(*num_block)?;
 
-            if let Some(last_winner) = maybe_last_winner.filter(|_| boards.is_empty()) {
-                return (last_winner.sum_unmarked() * num).to_string();
+    let mut grids: Vec<BingoBoard> = Vec::new();
+    for block in board_blocks {
+        let rows = block.lines().collect_vec();
+        let size = rows.len();
+        let mut gridx: Vec<i32> = Vec::with_capacity(size * size);
+        for row in rows {
+            let row_nums: Vec<i32> = parse::whitespace_separated(row)?;
+            if row_nums.len() != size {
+                return Err(row.error(format!(
+                    "expected {} numbers for a {}x{} board, found {}",
+                    size,
+                    size,
+                    size,
+                    row_nums.len()
+                )));
             }
+            gridx.extend(row_nums);
         }
+        grids.push(BingoBoard::new(size, gridx));
+    }
+
+    Ok((num_seq, grids))
+}
 
-        String::from("No solution")
+/// Which lines of marked numbers win. Rows and columns always do.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct BingoRules {
+    /// Whether the two corner-to-corner diagonals also win.
+    pub diagonals: bool,
+}
+
+/// When a board won.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Win {
+    /// How many numbers had been drawn, counting the winning one.
+    pub turn: usize,
+    /// The number that completed the board's first line.
+    pub number: i32,
+    pub unmarked_sum: i32,
+}
+
+impl Win {
+    pub fn score(&self) -> i64 {
+        self.unmarked_sum as i64 * self.number as i64
     }
 }
 
-const BOARD_SIZE: usize = 5;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BoardResult {
+    /// The board's index in the input.
+    pub board: usize,
+    /// `None` if the board never won.
+    pub win: Option<Win>,
+}
 
-fn parse_input(input: &str) -> (Vec<i32>, Vec<BingoBoard>) {
-    let mut lines = input.trim().lines();
-    let num_seq: Vec<i32> = lines
-        .next()
-        .map(|line| line.split(',').map(|num| num.parse::<i32>().unwrap()))
-        .unwrap()
-        .collect();
+/// Every board in the order they win, ties going to the board that comes first in the input,
+/// followed by the boards that never win.
+pub fn rank_boards(numbers: &[i32], boards: &[BingoBoard], rules: BingoRules) -> Vec<BoardResult> {
+    let mut game = BingoGame::new(boards, rules);
+    let mut ranking = Vec::with_capacity(boards.len());
+    for &number in numbers {
+        if game.all_won() {
+            break;
+        }
+        ranking.extend(game.draw(number));
+    }
 
-    let board_inputs = lines
-        .map(|it| it.trim())
-        .filter(|it| !it.is_empty())
-        .chunks(BOARD_SIZE);
+    let mut won = vec![false; boards.len()];
+    ranking.iter().for_each(|result| won[result.board] = true);
+    ranking.extend(
+        (0..boards.len())
+            .filter(|&index| !won[index])
+            .map(|board| BoardResult { board, win: None }),
+    );
+    ranking
+}
 
-    let mut grids: Vec<BingoBoard> = Vec::new();
-    for chunk in &board_inputs {
-        let gridx: Vec<i32> = chunk
-            .flat_map(|row_str| row_str.split_whitespace())
-            .map(|it| it.parse::<i32>().unwrap())
-            .collect();
-        grids.push(BingoBoard::new(BOARD_SIZE, gridx));
+/// How a board fared over many games with the draws shuffled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoardOdds {
+    pub board: usize,
+    /// The chance of winning first. Boards that win together on the first winning turn share it.
+    pub first: f64,
+    /// The chance of winning last, shared the same way.
+    pub last: f64,
+    /// The chance of winning at all, before the draws run out.
+    pub wins: f64,
+    /// The average turn the board won on, over the games it won; `None` if it never did.
+    pub expected_turn: Option<f64>,
+}
+
+/// Plays `trials` games with the draws shuffled by an RNG seeded from `seed`, so the same seed
+/// always gives the same odds.
+pub fn estimate_odds(
+    numbers: &[i32],
+    boards: &[BingoBoard],
+    rules: BingoRules,
+    trials: usize,
+    seed: u64,
+) -> Vec<BoardOdds> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut game = BingoGame::new(boards, rules);
+    let mut draws = numbers.to_vec();
+
+    let mut first = vec![0.0; boards.len()];
+    let mut last = vec![0.0; boards.len()];
+    let mut wins = vec![0usize; boards.len()];
+    let mut turn_totals = vec![0usize; boards.len()];
+
+    for _ in 0..trials {
+        draws.shuffle(&mut rng);
+        game.reset();
+
+        let mut first_winners: Vec<usize> = Vec::new();
+        let mut last_winners: Vec<usize> = Vec::new();
+        for &number in &draws {
+            if game.all_won() {
+                break;
+            }
+            let winners = game.draw(number);
+            if winners.is_empty() {
+                continue;
+            }
+            for result in &winners {
+                wins[result.board] += 1;
+                turn_totals[result.board] += result.win.unwrap().turn;
+            }
+            last_winners = winners.iter().map(|result| result.board).collect();
+            if first_winners.is_empty() {
+                first_winners = last_winners.clone();
+            }
+        }
+
+        for board in &first_winners {
+            first[*board] += 1.0 / first_winners.len() as f64;
+        }
+        for board in &last_winners {
+            last[*board] += 1.0 / last_winners.len() as f64;
+        }
     }
 
-    (num_seq, grids)
+    let trials_f = trials as f64;
+    (0..boards.len())
+        .map(|board| BoardOdds {
+            board,
+            first: first[board] / trials_f,
+            last: last[board] / trials_f,
+            wins: wins[board] as f64 / trials_f,
+            expected_turn: (wins[board] > 0)
+                .then(|| turn_totals[board] as f64 / wins[board] as f64),
+        })
+        .collect()
 }
 
+/// Plays a set of boards through a sequence of draws. Every number is indexed to the cells that
+/// hold it and each board counts the marks in every row, column and diagonal, so a draw only
+/// touches the boards that have its number.
 #[derive(Debug, Clone)]
-struct BingoBoard {
-    grid: Vec<(bool, i32)>,
-    size: usize,
+pub struct BingoGame<'a> {
+    boards: &'a [BingoBoard],
+    rules: BingoRules,
+    /// Where each number appears, as (board, cell) pairs in board order.
+    cells_by_number: HashMap<i32, Vec<(usize, usize)>>,
+    states: Vec<BoardState>,
+    turn: usize,
+    winners: usize,
 }
 
-impl BingoBoard {
-    fn new(size: usize, nums: Vec<i32>) -> BingoBoard {
-        BingoBoard {
-            grid: nums.iter().map(|&it| (false, it)).collect(),
-            size,
+#[derive(Debug, Clone)]
+struct BoardState {
+    marked: Vec<bool>,
+    row_marks: Vec<usize>,
+    column_marks: Vec<usize>,
+    /// Top left to bottom right, then top right to bottom left.
+    diagonal_marks: [usize; 2],
+    unmarked_sum: i32,
+    won: bool,
+}
+
+impl BoardState {
+    fn new(board: &BingoBoard) -> BoardState {
+        BoardState {
+            marked: vec![false; board.grid.len()],
+            row_marks: vec![0; board.size],
+            column_marks: vec![0; board.size],
+            diagonal_marks: [0; 2],
+            unmarked_sum: board.grid.iter().sum(),
+            won: false,
         }
     }
 
-    fn mark_value(&mut self, value: &i32) {
-        let found: Option<(usize, &(bool, i32))> =
-            self.grid.iter().find_position(|(_, v)| *v == *value);
-        if let Some((found_index, &el)) = found {
-            self.grid[found_index] = (true, el.1);
+    /// Marks `cell`, returning whether that completed a winning line.
+    fn mark(&mut self, board: &BingoBoard, cell: usize, rules: BingoRules) -> bool {
+        if self.marked[cell] {
+            return false;
         }
+        self.marked[cell] = true;
+        self.unmarked_sum -= board.grid[cell];
+
+        let size = board.size;
+        let (row, column) = (cell / size, cell % size);
+        self.row_marks[row] += 1;
+        self.column_marks[column] += 1;
+        let mut complete = self.row_marks[row] == size || self.column_marks[column] == size;
+        if rules.diagonals {
+            if row == column {
+                self.diagonal_marks[0] += 1;
+                complete |= self.diagonal_marks[0] == size;
+            }
+            if row + column == size - 1 {
+                self.diagonal_marks[1] += 1;
+                complete |= self.diagonal_marks[1] == size;
+            }
+        }
+        complete
     }
+}
 
-    fn sum_unmarked(&self) -> i32 {
-        self.grid
-            .iter()
-            .map(|(marked, value)| if !marked { *value } else { 0 })
-            .sum()
-    }
-
-    fn is_winner(&self) -> bool {
-        let flattened_marks: Vec<bool> = self.grid.iter().map(|(marked, _)| *marked).collect();
-        let row_win = (0..self.size).any(|row_i| {
-            flattened_marks
-                .iter()
-                .skip(row_i * self.size)
-                .take(self.size)
-                .all(|it| *it)
-        });
-        row_win
-            || (0..self.size).any(|col_i| {
-                flattened_marks
-                    .iter()
-                    .skip(col_i)
-                    .step_by(self.size)
-                    .all(|it| *it)
-            })
+impl<'a> BingoGame<'a> {
+    pub fn new(boards: &'a [BingoBoard], rules: BingoRules) -> BingoGame<'a> {
+        let mut cells_by_number: HashMap<i32, Vec<(usize, usize)>> = HashMap::new();
+        for (board_index, board) in boards.iter().enumerate() {
+            for (cell, &value) in board.grid.iter().enumerate() {
+                cells_by_number
+                    .entry(value)
+                    .or_default()
+                    .push((board_index, cell));
+            }
+        }
+
+        BingoGame {
+            boards,
+            rules,
+            cells_by_number,
+            states: boards.iter().map(BoardState::new).collect(),
+            turn: 0,
+            winners: 0,
+        }
+    }
+
+    /// Clears every mark to play again with another sequence of draws.
+    pub fn reset(&mut self) {
+        self.states = self.boards.iter().map(BoardState::new).collect();
+        self.turn = 0;
+        self.winners = 0;
+    }
+
+    /// Marks `number` on every board that hasn't won yet, returning the boards that won with it.
+    pub fn draw(&mut self, number: i32) -> Vec<BoardResult> {
+        self.turn += 1;
+        let mut results = Vec::new();
+        let cells = match self.cells_by_number.get(&number) {
+            Some(cells) => cells,
+            None => return results,
+        };
+
+        for &(board_index, cell) in cells {
+            let state = &mut self.states[board_index];
+            if state.won || !state.mark(&self.boards[board_index], cell, self.rules) {
+                continue;
+            }
+            state.won = true;
+            self.winners += 1;
+            results.push(BoardResult {
+                board: board_index,
+                win: Some(Win {
+                    turn: self.turn,
+                    number,
+                    unmarked_sum: state.unmarked_sum,
+                }),
+            });
+        }
+        results
+    }
+
+    pub fn all_won(&self) -> bool {
+        self.winners == self.boards.len()
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BingoBoard {
+    /// Each cell's number, row by row.
+    pub grid: Vec<i32>,
+    pub size: usize,
+}
+
+impl BingoBoard {
+    fn new(size: usize, grid: Vec<i32>) -> BingoBoard {
+        BingoBoard { grid, size }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day04::Day04;
+    use crate::days::day04::{
+        estimate_odds, parse_input, rank_boards, BingoRules, BoardOdds, BoardResult, Day04, Win,
+    };
     use crate::Solution;
+    use itertools::Itertools;
 
-    const SAMPLE_1: &'static str =
-        "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
+    const SAMPLE_1: &str = "7,4,9,5,11,17,23,2,0,14,21,24,10,16,13,6,15,25,12,22,18,20,8,19,3,26,1
 
 22 13 17 11  0
  8  2 23  4 24
@@ -148,4 +374,126 @@ mod tests {
     fn test_sample_part2() {
         assert_eq!(1924.to_string(), Day04 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_sample_ranking() {
+        let (numbers, boards) = parse_input(SAMPLE_1).unwrap();
+        let ranking = rank_boards(&numbers, &boards, BingoRules::default());
+        let order: Vec<(usize, usize)> = ranking
+            .iter()
+            .map(|result| (result.board, result.win.unwrap().turn))
+            .collect();
+        assert_eq!(vec![(2, 12), (0, 14), (1, 15)], order);
+        assert_eq!(
+            Some(Win {
+                turn: 12,
+                number: 24,
+                unmarked_sum: 188
+            }),
+            ranking[0].win
+        );
+    }
+
+    #[test]
+    fn test_other_sizes_and_diagonals() {
+        let input = "5,1,9,2
+
+1 2 3
+4 5 6
+7 8 9
+
+10 11
+12 13";
+        let (numbers, boards) = parse_input(input).unwrap();
+        assert_eq!(
+            vec![3, 2],
+            boards.iter().map(|b| b.size).collect::<Vec<_>>()
+        );
+
+        let ranking = rank_boards(&numbers, &boards, BingoRules::default());
+        assert_eq!(
+            vec![
+                BoardResult {
+                    board: 0,
+                    win: None
+                },
+                BoardResult {
+                    board: 1,
+                    win: None
+                }
+            ],
+            ranking
+        );
+
+        let ranking = rank_boards(&numbers, &boards, BingoRules { diagonals: true });
+        assert_eq!(
+            Some(Win {
+                turn: 3,
+                number: 9,
+                unmarked_sum: 30
+            }),
+            ranking[0].win
+        );
+        assert_eq!(None, ranking[1].win);
+    }
+
+    #[test]
+    fn test_many_boards() {
+        // board b holds b..b+9, so draws 0, 1, 2, ... finish the top row of board b on turn b + 3
+        let input = format!(
+            "{}\n\n{}",
+            (0..20_010).join(","),
+            (0..20_000)
+                .map(|b| (b..b + 9)
+                    .chunks(3)
+                    .into_iter()
+                    .map(|mut row| row.join(" "))
+                    .join("\n"))
+                .join("\n\n")
+        );
+        let (numbers, boards) = parse_input(&input).unwrap();
+        let ranking = rank_boards(&numbers, &boards, BingoRules::default());
+        assert_eq!(20_000, ranking.len());
+        for (b, result) in ranking.iter().enumerate() {
+            let win = result.win.unwrap();
+            assert_eq!(
+                (b, b + 3, b as i32 + 2),
+                (result.board, win.turn, win.number)
+            );
+        }
+    }
+
+    #[test]
+    fn test_estimated_odds() {
+        let (numbers, boards) = parse_input(SAMPLE_1).unwrap();
+        let odds = estimate_odds(&numbers, &boards, BingoRules::default(), 2000, 2021);
+        assert_eq!(
+            odds,
+            estimate_odds(&numbers, &boards, BingoRules::default(), 2000, 2021)
+        );
+
+        let total = |chance: fn(&BoardOdds) -> f64| odds.iter().map(chance).sum::<f64>();
+        assert!((total(|o| o.first) - 1.0).abs() < 1e-9);
+        assert!((total(|o| o.last) - 1.0).abs() < 1e-9);
+        for board_odds in &odds {
+            // every number is drawn eventually, so every board wins
+            assert_eq!(1.0, board_odds.wins);
+            let turn = board_odds.expected_turn.unwrap();
+            assert!((5.0..=27.0).contains(&turn), "{:?}", board_odds);
+        }
+    }
+
+    #[test]
+    fn test_odds_of_a_board_that_cannot_win() {
+        let (numbers, boards) = parse_input("1,2,3,4\n\n1 2\n3 4\n\n5 6\n7 8").unwrap();
+        let odds = estimate_odds(&numbers, &boards, BingoRules::default(), 100, 7);
+        assert_eq!((1.0, 1.0, 1.0), (odds[0].first, odds[0].last, odds[0].wins));
+        assert_eq!((0.0, None), (odds[1].wins, odds[1].expected_turn));
+    }
+
+    #[test]
+    fn test_boards_must_be_square() {
+        let error = parse_input("1,2\n\n1 2\n3 4 5").unwrap_err();
+        assert_eq!(4, error.line);
+    }
 }
@@ -1,120 +1,449 @@
+use crate::days::geometry;
+use crate::days::image::{Image, Palette, Rgb};
+use crate::days::interval::{Cuboid, Interval, IntervalSet};
+use crate::days::parse::{self, ParseResult, Span};
 use crate::days::template::Solution;
-use std::cmp::Ordering;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
 
 pub struct Day05 {}
 
 impl Solution for Day05 {
     fn part1(&self, input: &str) -> String {
-        Self::compute_result(input, |segment| segment.horiz_vert_points())
+        let segments = Self::parse_input(input).expect("Invalid input");
+        count_overlaps(&segments, false).to_string()
     }
 
     fn part2(&self, input: &str) -> String {
-        Self::compute_result(input, |segment| segment.points())
+        let segments = Self::parse_input(input).expect("Invalid input");
+        count_overlaps(&segments, true).to_string()
+    }
+
+    fn to_image(&self, input: &str, palette: Option<&Palette>) -> Option<Image> {
+        let segments = Self::parse_input(input).expect("Invalid input");
+        let points = segments.iter().flat_map(|segment| segment.points());
+        let default = Palette::Gradient(Rgb::BLACK, Rgb(255, 200, 0));
+        Some(Image::from_points(points, palette.unwrap_or(&default)))
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self, input: &str) -> Option<serde_json::Value> {
+        let segments = Self::parse_input(input).expect("Invalid input");
+        Some(serde_json::json!(segments))
     }
 }
 
 impl Day05 {
-    fn parse_input(input: &str) -> Vec<LineSegment> {
-        input
-            .trim()
-            .lines()
-            .map(|line| {
-                let mut split = line.split(" -> ");
-                LineSegment {
-                    start: Point::from(split.next().unwrap()),
-                    end: Point::from(split.next().unwrap()),
+    /// Reads one `x1,y1 -> x2,y2` vent line per line, rejecting lines that aren't horizontal,
+    /// vertical or at 45°.
+    pub fn parse_input(input: &str) -> ParseResult<Vec<LineSegment>> {
+        parse::rule_lines(Span::new(input))?
+            .into_iter()
+            .map(|(start, end)| {
+                let segment = LineSegment {
+                    start: parse_point(start)?,
+                    end: parse_point(end)?,
+                };
+                match segment.orientation() {
+                    Some(_) => Ok(segment),
+                    None => Err(start.error(format!(
+                        "expected a horizontal, vertical or 45° line, found {}",
+                        segment
+                    ))),
                 }
             })
             .collect()
     }
+}
 
-    fn compute_result(input: &str, get_points: fn(&LineSegment) -> Vec<Point>) -> String {
-        let segments = Self::parse_input(input);
-        let mut map: HashMap<Point, u64> = HashMap::new();
+/// Which way a line runs. A single point counts as horizontal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Orientation {
+    Horizontal,
+    Vertical,
+    /// x and y increase together.
+    Diagonal,
+    /// y decreases as x increases.
+    AntiDiagonal,
+}
 
-        for seg in &segments {
-            for point in get_points(seg) {
-                let new_count = map.get(&point).map(|cur| cur + 1).unwrap_or(1);
-                map.insert(point, new_count);
-            }
+impl Orientation {
+    const ALL: [Orientation; 4] = [
+        Orientation::Horizontal,
+        Orientation::Vertical,
+        Orientation::Diagonal,
+        Orientation::AntiDiagonal,
+    ];
+
+    /// `(a, b)` such that `a * x + b * y` is the same everywhere along a line, telling apart
+    /// parallel lines.
+    fn key_form(self) -> (i128, i128) {
+        match self {
+            Orientation::Horizontal => (0, 1),
+            Orientation::Vertical => (1, 0),
+            Orientation::Diagonal => (-1, 1),
+            Orientation::AntiDiagonal => (1, 1),
         }
+    }
 
-        map.values().filter(|&&it| it > 1).count().to_string()
+    fn key(self, (x, y): (i128, i128)) -> i128 {
+        let (a, b) = self.key_form();
+        a * x + b * y
+    }
+
+    /// Where along its line a point is.
+    fn position(self, (x, y): (i128, i128)) -> i128 {
+        match self {
+            Orientation::Vertical => y,
+            _ => x,
+        }
+    }
+
+    /// The point on both the line `self` with `key` and the line `other` with `other_key`, if
+    /// they cross at an integer point.
+    fn crossing(self, key: i128, other: Orientation, other_key: i128) -> Option<(i128, i128)> {
+        let (a1, b1) = self.key_form();
+        let (a2, b2) = other.key_form();
+        let det = a1 * b2 - a2 * b1;
+        if det == 0 {
+            return None;
+        }
+        let (x, y) = (key * b2 - other_key * b1, a1 * other_key - a2 * key);
+        (x % det == 0 && y % det == 0).then(|| (x / det, y / det))
     }
 }
 
 #[derive(Debug, Clone)]
-struct LineSegment {
-    start: Point,
-    end: Point,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LineSegment {
+    pub start: Point,
+    pub end: Point,
+}
+
+impl fmt::Display for LineSegment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}", self.start, self.end)
+    }
 }
 
 impl LineSegment {
-    fn horiz_vert_points(&self) -> Vec<Point> {
-        if self.start.x == self.end.x || self.start.y == self.end.y {
-            self.points()
+    /// `None` for lines at any angle other than a multiple of 45°.
+    pub fn orientation(&self) -> Option<Orientation> {
+        let (dx, dy) = (
+            self.end.x as i128 - self.start.x as i128,
+            self.end.y as i128 - self.start.y as i128,
+        );
+        if dy == 0 {
+            Some(Orientation::Horizontal)
+        } else if dx == 0 {
+            Some(Orientation::Vertical)
+        } else if dx == dy {
+            Some(Orientation::Diagonal)
+        } else if dx == -dy {
+            Some(Orientation::AntiDiagonal)
         } else {
-            Vec::new()
+            None
         }
     }
 
-    fn points(&self) -> Vec<Point> {
-        let mut points: Vec<Point> = Vec::new();
+    /// Every integer point on the segment, from start to end.
+    pub fn points(&self) -> Vec<Point> {
+        assert!(
+            self.orientation().is_some(),
+            "{} -> {} isn't horizontal, vertical or at 45°",
+            self.start,
+            self.end
+        );
+        let step = self.start.direction_to(&self.end);
+        let steps = self
+            .start
+            .x
+            .abs_diff(self.end.x)
+            .max(self.start.y.abs_diff(self.end.y));
 
-        let x_step = self.x_step();
-        let y_step = self.y_step();
-        let mut x = self.start.x;
-        let mut y = self.start.y;
+        let mut points = Vec::new();
+        let mut point = self.start;
+        points.push(point);
+        for _ in 0..steps {
+            point += step;
+            points.push(point);
+        }
+        points
+    }
+
+    /// The segment as the key of its line and the interval of positions it covers along it.
+    fn on_line(&self, orientation: Orientation) -> (i128, Interval<i128>) {
+        let start = (self.start.x as i128, self.start.y as i128);
+        let end = (self.end.x as i128, self.end.y as i128);
+        let (a, b) = (orientation.position(start), orientation.position(end));
+        (
+            orientation.key(start),
+            Interval::new(a.min(b), a.max(b)).unwrap(),
+        )
+    }
+}
 
-        while points.last() != Some(&self.end.clone()) {
-            points.push(Point { x, y });
-            x += x_step;
-            y += y_step;
+/// How many points are covered by at least two segments, leaving out diagonal segments unless
+/// `diagonals` is set.
+pub fn count_overlaps(segments: &[LineSegment], diagonals: bool) -> u128 {
+    let kept = segments
+        .iter()
+        .filter(|segment| {
+            diagonals
+                || matches!(
+                    segment.orientation(),
+                    Some(Orientation::Horizontal | Orientation::Vertical)
+                )
+        })
+        .cloned()
+        .collect();
+    VentIndex::new(kept).overlap_count()
+}
+
+/// The segments on each line, as the interval of positions each covers along it and the
+/// segment's index, sorted by where they start.
+type Lines = BTreeMap<(Orientation, i128), Vec<(Interval<i128>, usize)>>;
+
+/// Vent segments indexed by the line each lies on, for asking which segments are where.
+#[derive(Debug, Clone)]
+pub struct VentIndex {
+    segments: Vec<LineSegment>,
+    lines: Lines,
+}
+
+impl VentIndex {
+    pub fn new(segments: Vec<LineSegment>) -> VentIndex {
+        let mut lines: Lines = BTreeMap::new();
+        for (index, segment) in segments.iter().enumerate() {
+            let orientation = segment
+                .orientation()
+                .expect("Segments must be horizontal, vertical or at 45°");
+            let (key, interval) = segment.on_line(orientation);
+            lines
+                .entry((orientation, key))
+                .or_default()
+                .push((interval, index));
+        }
+        for entries in lines.values_mut() {
+            entries.sort_unstable_by_key(|&(interval, index)| (interval.start(), index));
         }
 
-        points
+        VentIndex { segments, lines }
+    }
+
+    pub fn segments(&self) -> &[LineSegment] {
+        &self.segments
+    }
+
+    /// The indices of the segments that cover `point`, in order.
+    pub fn covering(&self, point: Point) -> Vec<usize> {
+        let point = (point.x as i128, point.y as i128);
+        let mut found: Vec<usize> = Orientation::ALL
+            .iter()
+            .filter_map(|&orientation| {
+                let entries = self.lines.get(&(orientation, orientation.key(point)))?;
+                Some(containing(entries, orientation.position(point)))
+            })
+            .flatten()
+            .collect();
+        found.sort_unstable();
+        found
     }
 
-    fn x_step(&self) -> i64 {
-        Self::step(self.start.x, self.end.x)
+    /// How many points are covered by at least two segments.
+    pub fn overlap_count(&self) -> u128 {
+        count_line_overlaps(&self.lines)
     }
 
-    fn y_step(&self) -> i64 {
-        Self::step(self.start.y, self.end.y)
+    /// How many points inside `area`, edges included, are covered by at least two segments.
+    pub fn overlap_count_in(&self, area: &Cuboid<i64, 2>) -> u128 {
+        let [xs, ys] = area.axes;
+        let (x0, x1) = (xs.start() as i128, xs.end() as i128);
+        let (y0, y1) = (ys.start() as i128, ys.end() as i128);
+
+        // the lines that pass through the area, each cut down to the part inside it
+        let mut clipped: Lines = BTreeMap::new();
+        for orientation in Orientation::ALL {
+            let (lowest, highest) = match orientation {
+                Orientation::Horizontal => (y0, y1),
+                Orientation::Vertical => (x0, x1),
+                Orientation::Diagonal => (y0 - x1, y1 - x0),
+                Orientation::AntiDiagonal => (x0 + y0, x1 + y1),
+            };
+            let lines = self
+                .lines
+                .range((orientation, lowest)..=(orientation, highest));
+            for (&(_, key), entries) in lines {
+                let inside = match orientation {
+                    Orientation::Horizontal => Interval::new(x0, x1),
+                    Orientation::Vertical => Interval::new(y0, y1),
+                    Orientation::Diagonal => Interval::new(x0.max(y0 - key), x1.min(y1 - key)),
+                    Orientation::AntiDiagonal => Interval::new(x0.max(key - y1), x1.min(key - y0)),
+                };
+                let inside = match inside {
+                    Some(inside) => inside,
+                    None => continue,
+                };
+                let kept: Vec<(Interval<i128>, usize)> = entries
+                    .iter()
+                    .filter_map(|(interval, index)| Some((interval.intersection(&inside)?, *index)))
+                    .collect();
+                if !kept.is_empty() {
+                    clipped.insert((orientation, key), kept);
+                }
+            }
+        }
+        count_line_overlaps(&clipped)
     }
 
-    fn step(start: i64, end: i64) -> i64 {
-        match start.cmp(&end) {
-            Ordering::Equal => 0,
-            Ordering::Less => 1,
-            Ordering::Greater => -1,
+    /// Every pair of segments sharing at least one point, as indices with the smaller first, in
+    /// order.
+    pub fn intersecting_pairs(&self) -> Vec<(usize, usize)> {
+        let mut pairs = Vec::new();
+
+        // segments on the same line, found by sweeping along it
+        for entries in self.lines.values() {
+            let mut active: Vec<(i128, usize)> = Vec::new();
+            for &(interval, index) in entries {
+                active.retain(|&(end, _)| end >= interval.start());
+                pairs.extend(
+                    active
+                        .iter()
+                        .map(|&(_, other)| (other.min(index), other.max(index))),
+                );
+                active.push((interval.end(), index));
+            }
+        }
+
+        // segments on lines that cross
+        let lines: Vec<_> = self.lines.iter().collect();
+        for (i, (&(orientation, key), entries)) in lines.iter().enumerate() {
+            for (&(other, other_key), other_entries) in &lines[i + 1..] {
+                if let Some(point) = orientation.crossing(key, other, other_key) {
+                    for a in containing(entries, orientation.position(point)) {
+                        for b in containing(other_entries, other.position(point)) {
+                            pairs.push((a.min(b), a.max(b)));
+                        }
+                    }
+                }
+            }
         }
+
+        pairs.sort_unstable();
+        pairs
     }
 }
 
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
-struct Point {
-    x: i64,
-    y: i64,
+/// The indices of the segments among `entries`, sorted by start, that cover `position`.
+fn containing(entries: &[(Interval<i128>, usize)], position: i128) -> Vec<usize> {
+    let started = entries.partition_point(|(interval, _)| interval.start() <= position);
+    entries[..started]
+        .iter()
+        .filter(|(interval, _)| interval.contains(position))
+        .map(|&(_, index)| index)
+        .collect()
 }
 
-impl Point {
-    fn from(s: &str) -> Point {
-        let mut coords = s.split(',').map(|it| it.parse::<i64>().unwrap());
-        Point {
-            x: coords.next().unwrap(),
-            y: coords.next().unwrap(),
+/// How many points are covered at least twice by the segments on `lines`.
+///
+/// Along each line the stretches covered twice come from sweeping over the segments' ends, and
+/// points covered by lines running in two different directions come from solving for where each
+/// pair of lines crosses, so the time taken doesn't depend on how long the segments are.
+fn count_line_overlaps(lines: &Lines) -> u128 {
+    let intervals_on = |entries: &[(Interval<i128>, usize)]| -> Vec<Interval<i128>> {
+        entries.iter().map(|&(interval, _)| interval).collect()
+    };
+
+    let covered_twice: BTreeMap<(Orientation, i128), IntervalSet<i128>> = lines
+        .iter()
+        .map(|(&line, entries)| (line, covered_at_least_twice(&intervals_on(entries))))
+        .filter(|(_, twice)| !twice.is_empty())
+        .collect();
+    let mut overlaps: u128 = covered_twice.values().map(|twice| twice.len()).sum();
+
+    // every point where lines in different directions cross, merging overlapping segments first
+    let covered: Vec<(Orientation, i128, IntervalSet<i128>)> = lines
+        .iter()
+        .map(|(&(orientation, key), entries)| {
+            (
+                orientation,
+                key,
+                intervals_on(entries).into_iter().collect(),
+            )
+        })
+        .collect();
+    let mut crossings: HashSet<(i128, i128)> = HashSet::new();
+    for (i, (orientation, key, intervals)) in covered.iter().enumerate() {
+        for (other, other_key, other_intervals) in &covered[i + 1..] {
+            if let Some(point) = orientation.crossing(*key, *other, *other_key) {
+                if intervals.contains(orientation.position(point))
+                    && other_intervals.contains(other.position(point))
+                {
+                    crossings.insert(point);
+                }
+            }
+        }
+    }
+
+    // a crossing is one more point covered twice, unless its own lines already counted it
+    for point in crossings {
+        let already_counted = Orientation::ALL
+            .iter()
+            .filter(|orientation| {
+                covered_twice
+                    .get(&(**orientation, orientation.key(point)))
+                    .is_some_and(|twice| twice.contains(orientation.position(point)))
+            })
+            .count() as u128;
+        overlaps = overlaps + 1 - already_counted;
+    }
+    overlaps
+}
+
+/// The positions covered by at least two of `intervals`.
+fn covered_at_least_twice(intervals: &[Interval<i128>]) -> IntervalSet<i128> {
+    // +1 where an interval starts and -1 just after it ends; at the same position the ends sort
+    // first, which at worst splits a stretch that the set joins back up
+    let mut events: Vec<(i128, i32)> = intervals
+        .iter()
+        .flat_map(|interval| [(interval.start(), 1), (interval.end() + 1, -1)])
+        .collect();
+    events.sort_unstable();
+
+    let mut twice = IntervalSet::new();
+    let mut depth = 0;
+    let mut since = 0;
+    for (at, change) in events {
+        let before = depth;
+        depth += change;
+        if before < 2 && depth >= 2 {
+            since = at;
+        } else if before >= 2 && depth < 2 {
+            twice.insert(Interval::new(since, at - 1).unwrap());
         }
     }
+    twice
+}
+
+pub type Point = geometry::Point<i64>;
+
+fn parse_point(span: Span) -> ParseResult<Point> {
+    match *parse::comma_separated::<i64>(span)?.as_slice() {
+        [x, y] => Ok(Point::new(x, y)),
+        _ => Err(span.error(format!("expected `x,y`, found `{}`", span.text))),
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day05::Day05;
+    use crate::days::counter::Counter;
+    use crate::days::day05::{count_overlaps, Day05, LineSegment, Point, VentIndex};
+    use crate::days::interval::{Cuboid, Interval};
     use crate::Solution;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
 
-    const SAMPLE_1: &'static str = "0,9 -> 5,9
+    const SAMPLE_1: &str = "0,9 -> 5,9
 8,0 -> 0,8
 9,4 -> 3,4
 2,2 -> 2,1
@@ -134,4 +463,112 @@ mod tests {
     fn test_sample_part2() {
         assert_eq!(12.to_string(), Day05 {}.part2(SAMPLE_1));
     }
+
+    /// Counts overlaps the slow way, point by point.
+    fn count_points(segments: &[LineSegment]) -> u128 {
+        let covered: Counter<Point, u64> = segments.iter().flat_map(|s| s.points()).collect();
+        covered.iter().filter(|&(_, count)| count > 1).count() as u128
+    }
+
+    /// Short segments in every direction around a 30x30 grid.
+    fn random_segments(rng: &mut StdRng, count: usize) -> Vec<LineSegment> {
+        (0..count)
+            .map(|_| {
+                let start = Point::new(rng.gen_range(0..30), rng.gen_range(0..30));
+                let length = rng.gen_range(0..15);
+                let (dx, dy) =
+                    [(1, 0), (0, 1), (1, 1), (1, -1), (-1, 0), (-1, 1)][rng.gen_range(0..6)];
+                LineSegment {
+                    start,
+                    end: Point::new(start.x + dx * length, start.y + dy * length),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_counting_points() {
+        let mut rng = StdRng::seed_from_u64(5);
+        for _ in 0..50 {
+            let segments = random_segments(&mut rng, 40);
+            assert_eq!(count_points(&segments), count_overlaps(&segments, true));
+        }
+    }
+
+    #[test]
+    fn test_huge_coordinates() {
+        let input = "0,0 -> 3000000000,0
+1000000000,0 -> 4000000000,0
+2000000000,-5 -> 2000000000,5
+3500000000,1 -> 3500000002,-1";
+        // the two horizontal lines share 2000000001 points, and the vertical line crosses them
+        // where they already overlap; the diagonal adds one crossing of its own
+        assert_eq!(2000000001.to_string(), Day05 {}.part1(input));
+        assert_eq!(2000000002.to_string(), Day05 {}.part2(input));
+    }
+
+    #[test]
+    fn test_sample_queries() {
+        let index = VentIndex::new(Day05::parse_input(SAMPLE_1).unwrap());
+        // 4,4 is on 8,0 -> 0,8, 9,4 -> 3,4 and 0,0 -> 8,8
+        assert_eq!(vec![1, 2, 8], index.covering(Point::new(4, 4)));
+        assert_eq!(Vec::<usize>::new(), index.covering(Point::new(9, 9)));
+
+        let area = |x0, y0, x1, y1| {
+            Cuboid::new([
+                Interval::new(x0, x1).unwrap(),
+                Interval::new(y0, y1).unwrap(),
+            ])
+        };
+        assert_eq!(12, index.overlap_count_in(&area(0, 0, 9, 9)));
+        assert_eq!(0, index.overlap_count_in(&area(100, 100, 200, 200)));
+
+        let pairs = index.intersecting_pairs();
+        assert!(pairs.contains(&(0, 6)));
+        assert!(pairs.contains(&(1, 8)));
+        assert!(!pairs.contains(&(0, 1)));
+    }
+
+    #[test]
+    fn test_queries_match_brute_force() {
+        let mut rng = StdRng::seed_from_u64(49);
+        let segments = random_segments(&mut rng, 60);
+        let index = VentIndex::new(segments.clone());
+
+        let pairs = index.intersecting_pairs();
+        for (i, a) in segments.iter().enumerate() {
+            for (j, b) in segments.iter().enumerate().skip(i + 1) {
+                let shared = a.points().iter().any(|p| b.points().contains(p));
+                assert_eq!(shared, pairs.contains(&(i, j)), "{} and {}", a, b);
+            }
+        }
+
+        for _ in 0..50 {
+            let (x0, y0) = (rng.gen_range(-5..30), rng.gen_range(-5..30));
+            let (x1, y1) = (x0 + rng.gen_range(0..20), y0 + rng.gen_range(0..20));
+            let area = Cuboid::new([
+                Interval::new(x0, x1).unwrap(),
+                Interval::new(y0, y1).unwrap(),
+            ]);
+            let inside: Vec<LineSegment> = segments
+                .iter()
+                .flat_map(|s| s.points())
+                .filter(|p| area.contains([p.x, p.y]))
+                .map(|p| LineSegment { start: p, end: p })
+                .collect();
+            assert_eq!(count_points(&inside), index.overlap_count_in(&area));
+
+            let point = Point::new(rng.gen_range(0..30), rng.gen_range(0..30));
+            let expected: Vec<usize> = (0..segments.len())
+                .filter(|&i| segments[i].points().contains(&point))
+                .collect();
+            assert_eq!(expected, index.covering(point));
+        }
+    }
+
+    #[test]
+    fn test_other_angles_are_rejected() {
+        let error = Day05::parse_input("0,9 -> 5,9\n0,0 -> 2,1").unwrap_err();
+        assert_eq!(2, error.line);
+    }
 }
@@ -1,93 +1,178 @@
+use crate::days::matrix::Matrix;
+use crate::days::parse::{ParseResult, Span};
+use crate::days::simulation::Simulation;
 use crate::days::template::Solution;
-use itertools::repeat_n;
+use num_bigint::BigUint;
 
 pub struct Day06 {}
 
 impl Solution for Day06 {
     fn part1(&self, input: &str) -> String {
-        let mut fish = Self::parse_input(input);
+        Self::fish_after_days(input, 80).to_string()
+    }
 
-        for _ in 1..=80 {
-            // create new fishies
-            let start_fish_this_gen = fish.len();
-            let num_new = fish.iter_mut().filter(|f| **f == 0).count();
-            repeat_n(8, num_new).for_each(|nf| fish.push(nf));
+    fn part2(&self, input: &str) -> String {
+        Self::fish_after_days(input, 256).to_string()
+    }
+}
 
-            // apply decrements
-            fish.iter_mut()
-                .take(start_fish_this_gen)
-                .for_each(|f| *f = if *f == 0 { 6 } else { *f - 1 });
-        }
+impl Day06 {
+    fn fish_after_days(input: &str, days: usize) -> BigUint {
+        let species = Species::LANTERNFISH;
+        let timers = Self::parse_input(input, species).expect("Invalid input");
+        let mut population = Population::new();
+        population.add_species(species, &timers);
+        population.advance(days);
+        population.total()
+    }
 
-        fish.len().to_string()
-    }
-
-    /*
-    Diff 0: 0
-    Diff 1: 0
-    Diff 2: 116
-    Diff 3: 45
-    Diff 4: 42
-    Diff 5: 48
-    Diff 6: 49
-    Diff 7: 0
-    Diff 8: 0
-    Diff 9: 116 // day 2 + day 0
-    Diff 10: 45 // day 3 + day 1
-    Diff 11: 158 // day 4 + day 2
-    Diff 12: 93 // day 5 + day 3
-    Diff 13: 91 // day 6 + day 4
-    Diff 14: 48 // day 7 + day 5
-    ...
-    */
-    fn part2(&self, input: &str) -> String {
-        let mut fish = Self::parse_input(input);
-        let original_fish_count = fish.len();
+    /// Reads comma-separated timers, each at most `species`' highest timer.
+    pub fn parse_input(input: &str, species: Species) -> ParseResult<Vec<usize>> {
+        Span::new(input)
+            .trim()
+            .split(",")
+            .map(|timer| {
+                let timer = timer.trim();
+                match timer.parse()? {
+                    value if value <= species.max_timer() => Ok(value),
+                    value => Err(timer.error(format!(
+                        "timer {} is above the highest timer {}",
+                        value,
+                        species.max_timer()
+                    ))),
+                }
+            })
+            .collect()
+    }
+}
 
-        // no new fish on day 0 (initial)
-        let mut new_fish_counts: [usize; 257] = [0; 257];
+/// How a species of fish breeds: a fish whose timer is at 0 spawns a new fish with its timer at
+/// `newborn`, and its own timer goes back to `reset`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Species {
+    pub reset: usize,
+    pub newborn: usize,
+}
 
-        // simulate normally through day 8
-        for fish_count in new_fish_counts.iter_mut().take(9).skip(1) {
-            // create new fishies
-            let start_fish_this_gen = fish.len();
-            let num_new = fish.iter_mut().filter(|f| **f == 0).count();
-            repeat_n(8, num_new).for_each(|nf| fish.push(nf));
+impl Species {
+    pub const LANTERNFISH: Species = Species {
+        reset: 6,
+        newborn: 8,
+    };
 
-            // apply decrements
-            fish.iter_mut()
-                .take(start_fish_this_gen)
-                .for_each(|f| *f = if *f == 0 { 6 } else { *f - 1 });
+    /// The highest timer a fish of this species can have.
+    pub fn max_timer(&self) -> usize {
+        self.reset.max(self.newborn)
+    }
 
-            *fish_count = num_new;
+    /// One day for fish counted by timer: every timer counts down, and each fish at 0 goes back
+    /// to `reset` and spawns one at `newborn`.
+    fn transitions(&self) -> Matrix {
+        let mut transitions = Matrix::zero(self.max_timer() + 1);
+        for timer in 1..=self.max_timer() {
+            transitions.add_transition(timer, timer - 1, 1);
         }
+        transitions.add_transition(0, self.reset, 1);
+        transitions.add_transition(0, self.newborn, 1);
+        transitions
+    }
+}
+
+/// How many fish of each species there are with each timer value. The counts are exact however
+/// many days go by; a step takes time proportional to the number of timer values, not fish, and
+/// [`Population::advance`] jumps ahead in a logarithmic number of matrix products.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Population {
+    /// Each species with its fish counted by timer, so `counts[t]` fish have timer `t`.
+    groups: Vec<(Species, Vec<BigUint>)>,
+}
+
+impl Population {
+    pub fn new() -> Population {
+        Population::default()
+    }
 
-        // diff(d) = diff(d - 9) + diff(d - 7)
-        for d in 9..=256 {
-            new_fish_counts[d] = new_fish_counts[d - 9] + new_fish_counts[d - 7];
+    /// Adds a fish of `species` for each of `timers`, which must be at most the species' highest
+    /// timer. Fish of the same species are counted together.
+    pub fn add_species(&mut self, species: Species, timers: &[usize]) {
+        let index = match self.groups.iter().position(|(s, _)| *s == species) {
+            Some(index) => index,
+            None => {
+                let counts = vec![BigUint::default(); species.max_timer() + 1];
+                self.groups.push((species, counts));
+                self.groups.len() - 1
+            }
+        };
+        let counts = &mut self.groups[index].1;
+        for &timer in timers {
+            assert!(
+                timer < counts.len(),
+                "Timer {} is above the species' highest timer {}",
+                timer,
+                counts.len() - 1
+            );
+            counts[timer] += 1u32;
         }
+    }
 
-        let result = new_fish_counts.iter().sum::<usize>() + original_fish_count;
-        result.to_string()
+    fn count_down(&mut self) {
+        for (species, counts) in &mut self.groups {
+            // every timer counts down, and the fish that were at 0 wrap round to the end
+            counts.rotate_left(1);
+            let spawning = std::mem::take(counts.last_mut().unwrap());
+            counts[species.reset] += &spawning;
+            counts[species.newborn] += spawning;
+        }
     }
-}
 
-impl Day06 {
-    fn parse_input(input: &str) -> Vec<i8> {
-        input
-            .trim()
-            .split(',')
-            .map(|it| it.parse::<i8>().unwrap())
+    pub fn total(&self) -> BigUint {
+        self.groups.iter().flat_map(|(_, counts)| counts).sum()
+    }
+
+    /// How many fish of each species there are, in the order they were first added.
+    pub fn species_totals(&self) -> Vec<(Species, BigUint)> {
+        self.groups
+            .iter()
+            .map(|(species, counts)| (*species, counts.iter().sum()))
             .collect()
     }
+
+    /// Moves `days` days on by raising each species' daily transitions to the power `days`,
+    /// without totting up the population along the way.
+    pub fn advance(&mut self, days: usize) {
+        for (species, counts) in &mut self.groups {
+            *counts = species.transitions().big_counts_after(counts, days as u64);
+        }
+    }
+
+    /// The total population at the start and after each of the next `days` days.
+    pub fn series(&mut self, days: usize) -> Vec<BigUint> {
+        let mut totals = Vec::with_capacity(days + 1);
+        totals.push(self.total());
+        totals.extend(self.run_for(days));
+        totals
+    }
+}
+
+impl Simulation for Population {
+    /// The total population after the step.
+    type Output = BigUint;
+
+    fn step(&mut self) -> BigUint {
+        self.count_down();
+        self.total()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day06::Day06;
+    use crate::days::day06::{Day06, Population, Species};
+    use crate::days::matrix::Matrix;
+    use crate::days::simulation::Simulation;
     use crate::Solution;
+    use num_bigint::BigUint;
 
-    const SAMPLE_1: &'static str = "3,4,3,1,2";
+    const SAMPLE_1: &str = "3,4,3,1,2";
 
     #[test]
     fn test_sample_part1() {
@@ -98,4 +183,119 @@ mod tests {
     fn test_sample_part2() {
         assert_eq!(26984457539usize.to_string(), Day06 {}.part2(SAMPLE_1));
     }
+
+    /// Every fish's timer, one day at a time.
+    fn simulate_each_fish(species: Species, mut fish: Vec<usize>, days: usize) -> Vec<usize> {
+        let mut totals = vec![fish.len()];
+        for _ in 0..days {
+            let spawning = fish.iter().filter(|&&f| f == 0).count();
+            fish.iter_mut()
+                .for_each(|f| *f = if *f == 0 { species.reset } else { *f - 1 });
+            fish.extend(std::iter::repeat_n(species.newborn, spawning));
+            totals.push(fish.len());
+        }
+        totals
+    }
+
+    #[test]
+    fn test_series_matches_each_fish() {
+        let fish = Day06::parse_input(SAMPLE_1, Species::LANTERNFISH).unwrap();
+        let rules = [
+            Species::LANTERNFISH,
+            Species {
+                reset: 4,
+                newborn: 2,
+            },
+            Species {
+                reset: 0,
+                newborn: 3,
+            },
+        ];
+        for species in rules {
+            let fish: Vec<usize> = fish.iter().map(|&f| f.min(species.max_timer())).collect();
+            let mut population = Population::new();
+            population.add_species(species, &fish);
+            let expected: Vec<BigUint> = simulate_each_fish(species, fish, 40)
+                .into_iter()
+                .map(BigUint::from)
+                .collect();
+            assert_eq!(expected, population.series(40));
+        }
+    }
+
+    #[test]
+    fn test_several_species() {
+        let slow = Species {
+            reset: 10,
+            newborn: 12,
+        };
+        let mut population = Population::new();
+        population.add_species(Species::LANTERNFISH, &[3, 4]);
+        population.add_species(slow, &[3]);
+        population.add_species(Species::LANTERNFISH, &[3, 1, 2]);
+        population.run_for(80);
+
+        let totals = population.species_totals();
+        assert_eq!(Species::LANTERNFISH, totals[0].0);
+        assert_eq!(BigUint::from(5934u32), totals[0].1);
+        let expected = *simulate_each_fish(slow, vec![3], 80).last().unwrap();
+        assert_eq!((slow, BigUint::from(expected)), totals[1]);
+        assert_eq!(&totals[0].1 + &totals[1].1, population.total());
+    }
+
+    #[test]
+    fn test_long_horizons_are_exact() {
+        let fish = Day06::parse_input(SAMPLE_1, Species::LANTERNFISH).unwrap();
+        let mut population = Population::new();
+        population.add_species(Species::LANTERNFISH, &fish);
+        population.advance(1_000_000);
+        let total = population.total();
+        assert!(total.bits() > 128);
+
+        // the same growth counted modulo a prime
+        let mut transitions = Matrix::zero(9);
+        for timer in 1..=8 {
+            transitions.add_transition(timer, timer - 1, 1);
+        }
+        transitions.add_transition(0, 6, 1);
+        transitions.add_transition(0, 8, 1);
+        let mut counts = vec![0; 9];
+        fish.iter().for_each(|&f| counts[f] += 1);
+        let modulus = 1_000_000_007;
+        let expected: u128 = transitions
+            .counts_after_mod(&counts, 1_000_000, modulus)
+            .iter()
+            .sum::<u128>()
+            % modulus;
+        assert_eq!(BigUint::from(expected), total % modulus);
+    }
+
+    #[test]
+    fn test_advance_matches_series() {
+        let slow = Species {
+            reset: 10,
+            newborn: 12,
+        };
+        let mut advanced = Population::new();
+        advanced.add_species(Species::LANTERNFISH, &[3, 4, 3, 1, 2]);
+        advanced.add_species(slow, &[0, 12]);
+        let mut stepped = advanced.clone();
+
+        advanced.advance(20_000);
+        assert_eq!(stepped.series(20_000).last(), Some(&advanced.total()));
+        assert_eq!(stepped, advanced);
+    }
+
+    #[test]
+    fn test_timer_above_species_maximum_is_rejected() {
+        let error = Day06::parse_input("3,4, 9,1", Species::LANTERNFISH).unwrap_err();
+        assert_eq!((1, 6), (error.line, error.column));
+        assert!(Day06::parse_input("3,4,x", Species::LANTERNFISH).is_err());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_adding_a_timer_above_species_maximum() {
+        Population::new().add_species(Species::LANTERNFISH, &[9]);
+    }
 }
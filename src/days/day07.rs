@@ -1,65 +1,72 @@
+use crate::days::parse::{self, Span};
 use crate::days::template::Solution;
 use itertools::Itertools;
-use std::iter::repeat_with;
 
 pub struct Day07 {}
 
 impl Solution for Day07 {
     fn part1(&self, input: &str) -> String {
-        let sorted: Vec<i32> = Self::sorted_input(input);
-        let median: i32 = sorted[sorted.len() / 2];
-        let result: i32 = sorted.iter().map(|n| (median - n).abs()).sum();
-        result.to_string()
+        let sorted: Vec<i64> = Self::sorted_input(input);
+        let median: i64 = sorted[sorted.len() / 2];
+        Self::total_cost_for_target(&sorted, median, Some)
+            .expect("Fuel cost doesn't fit in a u64")
+            .to_string()
     }
 
     fn part2(&self, input: &str) -> String {
-        let sorted: Vec<i32> = Self::sorted_input(input);
-        // the optimal target might be anywhere from the min to max input number, at least naively
-        let start_range = *sorted.first().unwrap();
-        let end_range = *sorted.last().unwrap();
-        let result = (start_range..=end_range)
-            .min_by_key(|target| Self::total_cost_for_target(&sorted, target))
-            .map(|chosen_target| Self::total_cost_for_target(&sorted, &chosen_target))
-            .unwrap();
-        result.to_string()
+        let sorted: Vec<i64> = Self::sorted_input(input);
+        // the total cost is convex with its real minimum within half a position of the mean, so
+        // only the integers either side of the mean need trying
+        let sum: i128 = sorted.iter().map(|&n| n as i128).sum();
+        let floor_mean = sum.div_euclid(sorted.len() as i128);
+        // a target whose cost overflows can't be the cheapest, so it's skipped
+        (floor_mean - 1..=floor_mean + 2)
+            .filter_map(|target| i64::try_from(target).ok())
+            .filter_map(|target| Self::total_cost_for_target(&sorted, target, triangle_number))
+            .min()
+            .expect("Fuel cost doesn't fit in a u64")
+            .to_string()
     }
 }
 
 impl Day07 {
-    fn sorted_input(input: &str) -> Vec<i32> {
-        input
-            .trim()
-            .split(',')
-            .map(|it| it.parse::<i32>().unwrap())
+    fn sorted_input(input: &str) -> Vec<i64> {
+        parse::comma_separated::<i64>(Span::new(input))
+            .expect("Invalid input")
+            .into_iter()
             .sorted()
             .collect_vec()
     }
 
-    fn total_cost_for_target(sorted: &[i32], target: &i32) -> i32 {
-        let cost_fn: fn(i32, i32) -> i32 = |value: i32, target: i32| {
-            let mut cost = 0;
-            let mut cost_inc = 1;
+    /// The fuel for every crab to move to `target`, or `None` if it doesn't fit in a u64.
+    fn total_cost_for_target(
+        sorted: &[i64],
+        target: i64,
+        cost_fn: fn(u64) -> Option<u64>,
+    ) -> Option<u64> {
+        sorted.iter().try_fold(0u64, |total, n| {
+            total.checked_add(cost_fn(n.abs_diff(target))?)
+        })
+    }
+}
 
-            // trying this unnecessary trick out
-            repeat_with(|| {
-                cost += cost_inc;
-                cost_inc += 1;
-                cost
-            })
-            .take((target - value).abs() as usize)
-            .last()
-            .unwrap_or(0)
-        };
-        sorted.iter().map(|n| cost_fn(*n, *target)).sum::<i32>()
+/// 1 + 2 + ... + n, or `None` if it doesn't fit in a u64.
+fn triangle_number(n: u64) -> Option<u64> {
+    // halve whichever of n and n + 1 is even first, so the product only overflows if the result does
+    if n.is_multiple_of(2) {
+        (n / 2).checked_mul(n.checked_add(1)?)
+    } else {
+        n.checked_mul(n / 2 + 1)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day07::Day07;
+    use crate::days::day07::{triangle_number, Day07};
     use crate::Solution;
+    use itertools::Itertools;
 
-    const SAMPLE_1: &'static str = "16,1,2,0,4,2,7,1,2,14";
+    const SAMPLE_1: &str = "16,1,2,0,4,2,7,1,2,14";
 
     #[test]
     fn test_sample_part1() {
@@ -70,4 +77,48 @@ mod tests {
     fn test_sample_part2() {
         assert_eq!(168.to_string(), Day07 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_triangle_number() {
+        assert_eq!(Some(66), triangle_number(11));
+        // the largest that fits, and the first that doesn't
+        assert_eq!(Some(18446744070963499500), triangle_number(6074000999));
+        assert_eq!(None, triangle_number(6074001000));
+        assert_eq!(None, triangle_number(u64::MAX));
+    }
+
+    #[test]
+    fn test_costs_beyond_i32() {
+        // positions and costs an i32 can't hold
+        assert_eq!(
+            (4_000_000_000u64 + 3_000_000_000).to_string(),
+            Day07 {}.part1("-4000000000,0,3000000000")
+        );
+
+        let sorted = [i64::MIN, i64::MIN, i64::MAX, i64::MAX];
+        assert_eq!(None, Day07::total_cost_for_target(&sorted, i64::MAX, Some));
+        assert_eq!(
+            None,
+            Day07::total_cost_for_target(&[0, 1 << 33], 0, triangle_number)
+        );
+    }
+
+    #[test]
+    fn test_part2_with_a_huge_spread() {
+        // six billion positions apart, so trying every target in between would never finish
+        assert_eq!(
+            (2 * 4_500_000_001_500_000_000u64).to_string(),
+            Day07 {}.part2("-3000000000,3000000000,0")
+        );
+
+        // near the top of the i64 range, checked against every target close to the mean
+        let sorted = [i64::MAX - 3_000_000_001, i64::MAX - 1, i64::MAX];
+        let mean = i64::MAX - 1_000_000_001;
+        let cheapest = (mean - 1000..=mean + 1000)
+            .filter_map(|target| Day07::total_cost_for_target(&sorted, target, triangle_number))
+            .min()
+            .unwrap();
+        let input = sorted.iter().join(",");
+        assert_eq!(cheapest.to_string(), Day07 {}.part2(&input));
+    }
 }
@@ -1,3 +1,5 @@
+use crate::days::counter::Counter;
+use crate::days::parse::{ParseResult, Span};
 use crate::days::template::Solution;
 use itertools::Itertools;
 use std::collections::{HashMap, HashSet};
@@ -7,23 +9,17 @@ pub struct Day08 {}
 impl Solution for Day08 {
     fn part1(&self, input: &str) -> String {
         let unique_segment_counts = [2, 4, 3, 7]; // 1: 2, 4: 4, 7: 3, 8: 7
-        input
-            .trim()
-            .lines()
-            .map(|line| {
-                line.split(" | ")
-                    .skip(1)
-                    .flat_map(|it| it.split_whitespace())
-                    .filter(|it| unique_segment_counts.contains(&it.len()))
-                    .count()
-            })
-            .sum::<usize>()
+        parse_input(input)
+            .expect("Invalid input")
+            .iter()
+            .flat_map(|display| display.output_shown)
+            .filter(|it| unique_segment_counts.contains(&it.len()))
+            .count()
             .to_string()
     }
 
     fn part2(&self, input: &str) -> String {
-        let mut displays: Vec<BrokenDisplayInfo> =
-            input.trim().lines().map(BrokenDisplayInfo::from).collect();
+        let mut displays = parse_input(input).expect("Invalid input");
 
         displays
             .iter_mut()
@@ -36,6 +32,7 @@ impl Solution for Day08 {
     }
 }
 
+#[derive(Debug)]
 struct BrokenDisplayInfo<'a> {
     sequences_shown: [&'a str; 10],
     output_shown: [&'a str; 4],
@@ -47,30 +44,46 @@ struct Decoder {
 
 const UNRESOLVED: i8 = -1;
 
-impl BrokenDisplayInfo<'_> {
-    fn from(line: &str) -> BrokenDisplayInfo {
-        let sequences_and_output: Vec<Vec<&str>> = line
-            .split(" | ")
-            .map(|part| part.split_whitespace().collect())
-            .collect();
-        let mut iter = sequences_and_output.iter();
-        let mut sequences_shown = [""; 10];
-        let mut output_shown = [""; 4];
-
-        for (i, shown) in iter.next().unwrap().iter().enumerate() {
-            sequences_shown[i] = shown
-        }
+/// One display per line: the ten patterns seen, ` | `, then the four output digits.
+fn parse_input(input: &str) -> ParseResult<Vec<BrokenDisplayInfo<'_>>> {
+    Span::new(input)
+        .trim()
+        .lines()
+        .map(|line| {
+            let (sequences, output) = line
+                .split_once(" | ")
+                .ok_or_else(|| line.error("expected `<10 patterns> | <4 digits>`"))?;
+            Ok(BrokenDisplayInfo {
+                sequences_shown: segment_patterns(sequences)?,
+                output_shown: segment_patterns(output)?,
+            })
+        })
+        .collect()
+}
 
-        for (i, output) in iter.next().unwrap().iter().enumerate() {
-            output_shown[i] = output;
+/// Exactly `N` whitespace-separated patterns of segments `a` to `g`.
+fn segment_patterns<'a, const N: usize>(span: Span<'a>) -> ParseResult<[&'a str; N]> {
+    let mut patterns = [""; N];
+    let mut count = 0;
+    for pattern in span.split_whitespace() {
+        if pattern.text.chars().any(|ch| !('a'..='g').contains(&ch)) {
+            return Err(pattern.error(format!(
+                "expected segments a to g, found `{}`",
+                pattern.text
+            )));
         }
-
-        BrokenDisplayInfo {
-            sequences_shown,
-            output_shown,
+        if count < N {
+            patterns[count] = pattern.text;
         }
+        count += 1;
     }
+    if count != N {
+        return Err(span.error(format!("expected {} patterns, found {}", N, count)));
+    }
+    Ok(patterns)
+}
 
+impl BrokenDisplayInfo<'_> {
     /*
     # of segments:
     0: 6
@@ -114,24 +127,22 @@ impl BrokenDisplayInfo<'_> {
             };
         }
 
-        let mut occurrences: HashMap<char, u32> = HashMap::new();
-        for c in self.sequences_shown.iter().flat_map(|it| it.chars()) {
-            *occurrences.entry(c).or_default() += 1;
-        }
+        let occurrences: Counter<char, u32> = self
+            .sequences_shown
+            .iter()
+            .flat_map(|it| it.chars())
+            .collect();
 
-        let mut occurrences_in_1478: HashMap<char, u32> = HashMap::new();
-        for ch in self
+        let occurrences_in_1478: Counter<char, u32> = self
             .sequences_shown
             .iter()
             .enumerate()
             .filter(|(i, _)| resolved_sequences[*i] != UNRESOLVED)
             .flat_map(|(_, shown)| shown.chars())
-        {
-            *occurrences_in_1478.entry(ch).or_default() += 1
-        }
+            .collect();
 
         // fake segments that must correspond to these real segments based on number of occurrences
-        for (shown_segment, times_appeared) in &occurrences {
+        for (shown_segment, times_appeared) in occurrences.iter() {
             let real_segment: Option<char> = match times_appeared {
                 6 => Some('b'),
                 4 => Some('e'),
@@ -173,18 +184,18 @@ impl BrokenDisplayInfo<'_> {
     }
 
     fn single_char_with_occurrences(
-        occurrences_in_1478: &HashMap<char, u32>,
-        occurrences: &HashMap<char, u32>,
+        occurrences_in_1478: &Counter<char, u32>,
+        occurrences: &Counter<char, u32>,
         in_1478: u32,
         in_all: u32,
     ) -> char {
         let mut possible: HashSet<char> = occurrences_in_1478
             .iter()
-            .filter(|(_, times)| **times == in_1478)
+            .filter(|&(_, times)| times == in_1478)
             .map(|(fake_ch, _)| *fake_ch)
             .collect();
 
-        possible.retain(|ch| occurrences.get(ch) == Some(&in_all));
+        possible.retain(|ch| occurrences.get(ch) == in_all);
 
         match *possible.into_iter().collect::<Vec<char>>().as_slice() {
             [single] => single,
@@ -202,7 +213,6 @@ impl Decoder {
                 .chars()
                 .map(|ch| self.letter_decoder.get(&ch).unwrap())
                 .sorted()
-                .into_iter()
                 .join("");
             decoded_strings.push(decoded);
         }
@@ -230,10 +240,10 @@ impl Decoder {
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day08::Day08;
+    use crate::days::day08::{parse_input, Day08};
     use crate::Solution;
 
-    const SAMPLE_1: &'static str =
+    const SAMPLE_1: &str =
         "be cfbegad cbdgef fgaecd cgeb fdcge agebfd fecdb fabcd edb | fdgacbe cefdb cefbgd gcbe
 edbfga begcd cbg gc gcadebf fbgde acbgfd abcde gfcbed gfec | fcgedb cgb dgebacf gc
 fgaebd cg bdaec gdafb agbcfd gdcbef bgcad gfac gcb cdgabef | cg cg fdcagb cbg
@@ -254,4 +264,14 @@ gcafb gcf dcaebfg ecagb gf abcdeg gaef cafbge fdbac fegbdc | fgae cfgab fg bagce
     fn test_sample_part2() {
         assert_eq!(61229.to_string(), Day08 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_malformed_displays() {
+        let error = parse_input("ab cd ef | ab cd ef ab").unwrap_err();
+        assert_eq!((1, 1), (error.line, error.column));
+        let line = SAMPLE_1.lines().next().unwrap();
+        let error = parse_input(&format!("{}\n{}x", line, line)).unwrap_err();
+        assert_eq!((2, 83), (error.line, error.column));
+        assert!(parse_input(&line.replace(" | ", " ")).is_err());
+    }
 }
@@ -1,10 +1,11 @@
+use crate::days::image::{Image, Palette, Rgb};
+use crate::days::parse::{self, Span};
 use crate::days::template::Solution;
+use crate::days::union_find;
 use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
 
 pub struct Day09 {}
 
-type Basin = usize;
 type Tile = usize;
 
 impl Solution for Day09 {
@@ -27,82 +28,33 @@ impl Solution for Day09 {
 
     fn part2(&self, input: &str) -> String {
         let (width, parsed_input) = Self::parse_input(input);
-        let total_len = parsed_input.len();
-
-        let mut seen: HashSet<Tile> = HashSet::new();
-        let mut count_by_basin: HashMap<Basin, i32> = HashMap::new();
-        let mut to_visit: Vec<Tile> = Vec::new();
 
-        for (i, tile_index) in parsed_input
-            .iter()
-            .enumerate()
-            .filter(|(_, tile_value)| **tile_value < 9)
-            .map(|(index, _)| index)
-            .enumerate()
-        {
-            let basin: Basin = i;
-
-            if !seen.contains(&tile_index) {
-                to_visit.push(tile_index);
-            }
-
-            while !to_visit.is_empty() {
-                let next: Tile = to_visit.pop().unwrap();
-
-                if seen.insert(next) {
-                    let new_count = *count_by_basin.get(&basin).unwrap_or(&0) + 1;
-                    count_by_basin.insert(basin, new_count);
-
-                    for a in
-                        Self::adjacent_indices(&parsed_input, width, total_len, &next, |&v| v < 9)
-                    {
-                        if !seen.contains(&a) {
-                            to_visit.push(a)
-                        }
-                    }
-                }
-            }
-        }
+        // basins are the areas between the height-9 ridges
+        let basins = union_find::label_grid(width, &parsed_input, |&height| height < 9);
 
-        let result: i32 = count_by_basin
-            .into_values()
-            .sorted()
-            .into_iter()
-            .rev()
-            .take(3)
-            .product();
+        let result: usize = basins.sizes.into_iter().sorted().rev().take(3).product();
         result.to_string()
     }
+
+    fn to_image(&self, input: &str, palette: Option<&Palette>) -> Option<Image> {
+        let (width, parsed_input) = Self::parse_input(input);
+        let heights = parsed_input.iter().map(|&it| it as u64);
+        // low points dark, the height-9 ridges between basins light
+        let default = Palette::Gradient(Rgb(8, 29, 88), Rgb(237, 248, 177));
+        Some(Image::from_grid(
+            width,
+            parsed_input.len() / width,
+            heights,
+            palette.unwrap_or(&default),
+        ))
+    }
 }
 
 impl Day09 {
     fn parse_input(input: &str) -> (usize, Vec<usize>) {
-        let trimmed = input.trim();
-        let width = trimmed.lines().next().unwrap().len();
-        let parsed = trimmed
-            .lines()
-            .flat_map(|line| {
-                line.trim()
-                    .chars()
-                    .map(|ch| ch.to_digit(10).unwrap() as usize)
-                    .collect::<Vec<usize>>()
-            })
-            .collect();
-        (width, parsed)
-    }
-
-    fn adjacent_indices(
-        parsed_input: &[usize],
-        width: usize,
-        total_len: usize,
-        index: &Tile,
-        value_filter: fn(&usize) -> bool,
-    ) -> Vec<Tile> {
-        Self::adjacent_values_with_indices(parsed_input, width, total_len, index)
-            .iter()
-            .filter(|(value, _)| value_filter(value))
-            .map(|(_, index)| *index)
-            .collect()
+        let grid = parse::digit_grid(Span::new(input)).expect("Invalid input");
+        let parsed = grid.values.iter().map(|&it| it as usize).collect();
+        (grid.width, parsed)
     }
 
     fn adjacent_values_with_indices<'a>(
@@ -113,7 +65,7 @@ impl Day09 {
     ) -> Vec<(&'a Tile, usize)> {
         let mut result: Vec<(&usize, usize)> = Vec::new();
 
-        if index % width != 0 {
+        if !index.is_multiple_of(width) {
             result.push(
                 parsed_input
                     .get(index - 1)
@@ -122,7 +74,7 @@ impl Day09 {
             )
         }
 
-        if (index + 1) % width != 0 {
+        if !(index + 1).is_multiple_of(width) {
             result.push(
                 parsed_input
                     .get(index + 1)
@@ -157,7 +109,7 @@ mod tests {
     use crate::days::day09::Day09;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "2199943210
+    const SAMPLE_1: &str = "2199943210
 3987894921
 9856789892
 8767896789
@@ -1,3 +1,4 @@
+use crate::days::parse::{ParseResult, Span};
 use crate::days::template::Solution;
 use std::collections::LinkedList;
 
@@ -5,7 +6,7 @@ pub struct Day10 {}
 
 impl Solution for Day10 {
     fn part1(&self, input: &str) -> String {
-        let parsed_input = Self::parse_input(input);
+        let parsed_input = Self::parse_input(input).expect("Invalid input");
 
         let mut syntax_score = 0;
         for line in parsed_input {
@@ -34,7 +35,7 @@ impl Solution for Day10 {
     }
 
     fn part2(&self, input: &str) -> String {
-        let parsed_input = Self::parse_input(input);
+        let parsed_input = Self::parse_input(input).expect("Invalid input");
 
         let mut all_scores: Vec<usize> = Vec::new();
         for line in parsed_input
@@ -70,8 +71,25 @@ impl Solution for Day10 {
 }
 
 impl Day10 {
-    fn parse_input(input: &str) -> Vec<&str> {
-        input.trim().lines().collect()
+    /// One line of brackets per line, rejecting any other character.
+    fn parse_input(input: &str) -> ParseResult<Vec<&str>> {
+        Span::new(input)
+            .trim()
+            .lines()
+            .map(|line| {
+                let line = line.trim();
+                match line
+                    .text
+                    .char_indices()
+                    .find(|&(_, ch)| !"()[]{}<>".contains(ch))
+                {
+                    Some((index, ch)) => Err(line
+                        .slice(index, index + ch.len_utf8())
+                        .error(format!("expected a bracket, found `{}`", ch))),
+                    None => Ok(line.text),
+                }
+            })
+            .collect()
     }
 
     fn open_for(ch: char) -> Option<char> {
@@ -92,7 +110,7 @@ mod tests {
     use crate::days::day10::Day10;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "[({(<(())[]>[[{[]{<()<>>
+    const SAMPLE_1: &str = "[({(<(())[]>[[{[]{<()<>>
 [(()[<>])]({[<{<<[]>>(
 {([(<{}[<>[]}>{[]{[(<()>
 (((({<>}<{<{<>}{[]{[]{}
@@ -112,4 +130,11 @@ mod tests {
     fn test_sample_part2() {
         assert_eq!(288957.to_string(), Day10 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_other_characters_are_rejected() {
+        let error = Day10::parse_input("[({(<(())[]>[[{[]{<()<>>\n[(()[<x])]").unwrap_err();
+        assert_eq!((2, 7), (error.line, error.column));
+        assert!(error.message.contains("`x`"));
+    }
 }
@@ -1,3 +1,5 @@
+use crate::days::parse::{self, Span};
+use crate::days::simulation::Simulation;
 use crate::days::template::Solution;
 use itertools::Itertools;
 use std::cmp::min;
@@ -9,23 +11,32 @@ pub struct Day11 {}
 impl Solution for Day11 {
     fn part1(&self, input: &str) -> String {
         let mut octopus_grid = Self::parse_input(input);
-        octopus_grid.do_octopus_things(100).to_string()
+        let total_flashes: usize = octopus_grid.run_for(100).iter().sum();
+        total_flashes.to_string()
     }
 
     fn part2(&self, input: &str) -> String {
         let mut octopus_grid = Self::parse_input(input);
-        octopus_grid.trick_the_octopuses().to_string()
+        octopus_grid
+            .run_until(|grid, &flashes| flashes == grid.grid.values.len())
+            .to_string()
     }
 }
 
 impl Day11 {
     fn parse_input(input: &str) -> FlashingOctopusGrid {
+        let digits = parse::digit_grid(Span::new(input)).expect("Invalid input");
         FlashingOctopusGrid {
-            grid: Grid::from(input, |ch| ch.to_digit(10).unwrap()),
+            grid: Grid {
+                values: digits.values,
+                width: digits.width,
+                height: digits.height,
+            },
         }
     }
 }
 
+#[derive(Clone, PartialEq, Eq, Hash)]
 struct FlashingOctopusGrid {
     grid: Grid<u32>,
 }
@@ -49,28 +60,17 @@ once per step.)
 Finally, any octopus that flashed during this step has its energy level set to 0, as it used all of
 its energy to flash.
  */
-impl FlashingOctopusGrid {
-    fn do_octopus_things(&mut self, steps: usize) -> usize {
-        let mut total_flashes = 0;
-        for _i in 1..=steps {
-            self.increment_all_energies();
-            total_flashes += self.flash();
-        }
-        total_flashes
-    }
+impl Simulation for FlashingOctopusGrid {
+    /// How many octopuses flashed.
+    type Output = usize;
 
-    fn trick_the_octopuses(&mut self) -> usize {
-        let mut i = 0;
-        loop {
-            i += 1;
-            self.increment_all_energies();
-            let flashes = self.flash();
-            if flashes == self.grid.values.len() {
-                return i;
-            }
-        }
+    fn step(&mut self) -> usize {
+        self.increment_all_energies();
+        self.flash()
     }
+}
 
+impl FlashingOctopusGrid {
     fn increment_all_energies(&mut self) {
         self.grid.values.iter_mut().for_each(|v| *v += 1)
     }
@@ -125,6 +125,7 @@ impl FlashingOctopusGrid {
     }
 }
 
+#[derive(Clone, PartialEq, Eq, Hash)]
 struct Grid<T> {
     values: Vec<T>,
     width: usize,
@@ -132,23 +133,6 @@ struct Grid<T> {
 }
 
 impl<T> Grid<T> {
-    fn from(str: &str, char_to_t: fn(char) -> T) -> Grid<T> {
-        let trimmed = str.trim();
-        let width = trimmed.lines().next().unwrap().len();
-        let height = trimmed.lines().count();
-
-        let values = trimmed
-            .lines()
-            .flat_map(|line| line.chars())
-            .map(char_to_t)
-            .collect();
-        Grid {
-            values,
-            width,
-            height,
-        }
-    }
-
     fn value_at(&self, x: isize, y: isize) -> Option<&T> {
         if !(0..self.width).contains(&usize::try_from(x).unwrap_or(self.width + 1))
             || !(0..self.height).contains(&usize::try_from(y).unwrap_or(self.height + 1))
@@ -177,7 +161,7 @@ impl<T> Grid<T> {
     fn adjacent_indices(&self, index: &usize) -> Vec<usize> {
         let (xu, yu) = self.index_to_xy(index).unwrap();
         let (xi, yi) = (xu as isize, yu as isize);
-        let adj_coords = vec![
+        let adj_coords = [
             (xi - 1, yi),
             (xi - 1, yi - 1),
             (xi - 1, yi + 1),
@@ -199,9 +183,10 @@ impl<T> Grid<T> {
 #[cfg(test)]
 mod tests {
     use crate::days::day11::Day11;
+    use crate::days::simulation::{Cycle, Simulation};
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "5483143223
+    const SAMPLE_1: &str = "5483143223
 2745854711
 5264556173
 6141336146
@@ -221,4 +206,18 @@ mod tests {
     fn test_sample_part2() {
         assert_eq!(195.to_string(), Day11 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_sample_cycles_once_synchronized() {
+        // after all flashing together they charge up and flash together every 10 steps
+        let mut octopus_grid = Day11::parse_input(SAMPLE_1);
+        let cycle = octopus_grid.find_cycle(1000).unwrap();
+        assert_eq!(
+            Cycle {
+                start: 195,
+                period: 10
+            },
+            cycle
+        );
+    }
 }
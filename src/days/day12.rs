@@ -1,7 +1,8 @@
-use crate::days::graph::Graph;
 use crate::days::graph::NodeIndex;
+use crate::days::graph::{DotStyle, Graph, NodeData};
 use crate::Solution;
-use std::collections::{HashMap, HashSet, LinkedList};
+use itertools::Itertools;
+use std::collections::{HashSet, LinkedList};
 
 pub struct Day12 {}
 
@@ -44,42 +45,28 @@ impl Solution for Day12 {
 
         all_paths.len().to_string()
     }
+
+    fn to_dot(&self, input: &str) -> Option<String> {
+        Some(Self::build_graph(input).to_dot("caves", &CaveDotStyle))
+    }
 }
 
 impl Day12 {
     fn build_graph(input: &str) -> Graph {
-        let mut graph = Graph {
-            nodes: Vec::new(),
-            edges: Vec::new(),
-            node_index_by_id: HashMap::new(),
-        };
-
-        for input_line in input.trim().lines() {
-            let split: Vec<&str> = input_line.split('-').collect();
-            let src = split.get(0).unwrap();
-            let dst = split.get(1).unwrap();
-            let src_n = graph.add_node(&src.to_string());
-            let dst_n = graph.add_node(&dst.to_string());
-
-            match (*src, *dst) {
-                // start and end edges are unidirectional, all other edges bidirectional
-                (START_ID, _) => {
-                    graph.add_edge(src_n, dst_n);
-                }
-                (END_ID, _) => {
-                    graph.add_edge(dst_n, src_n);
-                }
-                (_, START_ID) => {
-                    graph.add_edge(dst_n, src_n);
-                }
-                (_, END_ID) => {
-                    graph.add_edge(src_n, dst_n);
-                }
-                _ => {
-                    graph.add_edge(src_n, dst_n);
-                    graph.add_edge(dst_n, src_n);
-                }
-            };
+        // tunnels go both ways, except that start can only be left and end can only be entered
+        let mut graph: Graph = Graph::from_edge_list(input).expect("Invalid input");
+
+        if let Some(start) = graph.node_index(START_ID) {
+            for next in graph.successors(start).collect_vec() {
+                graph.remove_edge(start, next);
+                graph.add_edge(start, next);
+            }
+        }
+        if let Some(end) = graph.node_index(END_ID) {
+            for prev in graph.successors(end).collect_vec() {
+                graph.remove_edge(end, prev);
+                graph.add_edge(prev, end);
+            }
         }
         graph
     }
@@ -152,12 +139,29 @@ fn is_small_cave(id: &str) -> bool {
     id.to_ascii_lowercase() == id
 }
 
+/// Draws start/end as double circles, big caves as filled boxes and small caves as plain ellipses.
+struct CaveDotStyle;
+
+impl DotStyle for CaveDotStyle {
+    fn node_attributes(&self, node: &NodeData) -> Vec<(&'static str, String)> {
+        match node.id.as_str() {
+            START_ID | END_ID => vec![("shape", "doublecircle".to_string())],
+            id if is_small_cave(id) => vec![("shape", "ellipse".to_string())],
+            _ => vec![
+                ("shape", "box".to_string()),
+                ("style", "filled".to_string()),
+                ("fillcolor", "lightgray".to_string()),
+            ],
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::days::day12::Day12;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "start-A
+    const SAMPLE_1: &str = "start-A
 start-b
 A-c
 A-b
@@ -165,7 +169,7 @@ b-d
 A-end
 b-end";
 
-    const SAMPLE_2: &'static str = "dc-end
+    const SAMPLE_2: &str = "dc-end
 HN-start
 start-kj
 dc-start
@@ -176,7 +180,7 @@ kj-sa
 kj-HN
 kj-dc";
 
-    const SAMPLE_3: &'static str = "fs-end
+    const SAMPLE_3: &str = "fs-end
 he-DX
 fs-he
 start-DX
@@ -195,6 +199,23 @@ zg-he
 pj-fs
 start-RW";
 
+    #[test]
+    fn test_sample1_dot() {
+        let dot = Day12 {}.to_dot(SAMPLE_1).unwrap();
+        assert!(dot.starts_with("digraph \"caves\" {\n"));
+        assert!(dot.contains("    \"start\" [label=\"start\", shape=\"doublecircle\"];\n"));
+        assert!(dot.contains("    \"A\" [label=\"A\", shape=\"box\", style=\"filled\""));
+        assert!(dot.contains("    \"b\" [label=\"b\", shape=\"ellipse\"];\n"));
+        // start and end edges only go one way
+        assert!(dot.contains("    \"start\" -> \"A\";\n"));
+        assert!(!dot.contains("    \"A\" -> \"start\";\n"));
+        assert!(dot.contains("    \"A\" -> \"end\";\n"));
+        assert!(!dot.contains("    \"end\" -> \"A\";\n"));
+        // other tunnels go both ways
+        assert!(dot.contains("    \"A\" -> \"c\" [dir=\"none\"];\n"));
+        assert!(!dot.contains("    \"c\" -> \"A\""));
+    }
+
     #[test]
     fn test_sample1_part1() {
         assert_eq!(10.to_string(), Day12 {}.part1(SAMPLE_1));
@@ -1,4 +1,7 @@
 use crate::days::day13::Fold::{Left, Up};
+use crate::days::geometry;
+use crate::days::image::{Image, Palette, Rgb};
+use crate::days::parse::{self, ParseResult, Span};
 use crate::Solution;
 use itertools::Itertools;
 use std::cmp::max;
@@ -10,13 +13,13 @@ const CHAR_NO_FILL: char = '⚪';
 
 impl Solution for Day13 {
     fn part1(&self, input: &str) -> String {
-        let (points, folds) = parse_input(input);
+        let (points, folds) = parse_input(input).expect("Invalid input");
 
         let visible_points_1_fold = folds
             .iter()
             .take(1)
             .fold(points, |acc_points, fold| {
-                acc_points.iter().map(|p| p.translate(fold)).collect_vec()
+                acc_points.iter().map(|p| fold.translate(p)).collect_vec()
             })
             .into_iter()
             .unique()
@@ -26,16 +29,7 @@ impl Solution for Day13 {
     }
 
     fn part2(&self, input: &str) -> String {
-        let (points, folds) = parse_input(input);
-
-        let visible_points = folds
-            .iter()
-            .fold(points.iter().copied().collect_vec(), |acc_points, fold| {
-                acc_points.iter().map(|p| p.translate(fold)).collect_vec()
-            })
-            .into_iter()
-            .unique()
-            .collect_vec();
+        let visible_points = visible_points_after_folds(input);
 
         // draw the answer
         let (max_x, max_y) = &visible_points.iter().fold((0, 0), |(acc_x, acc_y), cur| {
@@ -45,7 +39,7 @@ impl Solution for Day13 {
         let mut chars: Vec<char> = vec!['\n'];
         for y in 0..=*max_y {
             for x in 0..=*max_x {
-                if visible_points.contains(&Point { x, y }) {
+                if visible_points.contains(&Point::new(x, y)) {
                     chars.push(CHAR_FILL)
                 } else {
                     chars.push(CHAR_NO_FILL)
@@ -56,86 +50,109 @@ impl Solution for Day13 {
 
         String::from_iter(chars)
     }
+
+    fn to_image(&self, input: &str, palette: Option<&Palette>) -> Option<Image> {
+        let default = Palette::Discrete(vec![Rgb::WHITE, Rgb::BLACK]);
+        Some(Image::from_points(
+            visible_points_after_folds(input),
+            palette.unwrap_or(&default),
+        ))
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self, input: &str) -> Option<serde_json::Value> {
+        let (points, folds) = parse_input(input).expect("Invalid input");
+        Some(serde_json::json!({ "points": points, "folds": folds }))
+    }
 }
 
-#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
-struct Point {
-    x: usize,
-    y: usize,
+fn visible_points_after_folds(input: &str) -> Vec<Point> {
+    let (points, folds) = parse_input(input).expect("Invalid input");
+
+    folds
+        .iter()
+        .fold(points, |acc_points, fold| {
+            acc_points.iter().map(|p| fold.translate(p)).collect_vec()
+        })
+        .into_iter()
+        .unique()
+        .collect_vec()
 }
 
-impl Point {
-    fn from(point_str: &str) -> Point {
-        match *point_str.split(',').collect_vec().as_slice() {
-            [x, y] => Point {
-                x: x.parse().unwrap(),
-                y: y.parse().unwrap(),
-            },
-            _ => panic!("Bad point: {}", point_str),
-        }
-    }
+type Point = geometry::Point<usize>;
 
-    fn translate(&self, fold: &Fold) -> Point {
-        match fold {
-            Up(y_coord) => {
-                if self.y > *y_coord {
-                    let offset = (self.y - y_coord) * 2;
-                    Point {
-                        x: self.x,
-                        y: self.y - offset,
-                    }
-                } else {
-                    *self
-                }
-            }
-            Left(x_coord) => {
-                if self.x > *x_coord {
-                    let offset = (self.x - x_coord) * 2;
-                    Point {
-                        x: self.x - offset,
-                        y: self.y,
-                    }
-                } else {
-                    *self
-                }
-            }
-        }
+fn parse_point(span: Span) -> ParseResult<Point> {
+    match *parse::comma_separated::<usize>(span)?.as_slice() {
+        [x, y] => Ok(Point::new(x, y)),
+        _ => Err(span.error(format!("expected `x,y`, found `{}`", span.text))),
     }
 }
 
-fn parse_input(input: &str) -> (Vec<Point>, Vec<Fold>) {
-    let (point_data, fold_data) = input.trim().split_once("\n\n").unwrap();
+fn parse_input(input: &str) -> ParseResult<(Vec<Point>, Vec<Fold>)> {
+    let (point_data, fold_data) = match *parse::blocks(Span::new(input)).as_slice() {
+        [point_data, fold_data] => (point_data, fold_data),
+        _ => return Err(Span::new(input).error("expected a block of points and a block of folds")),
+    };
 
-    let points = point_data.trim().lines().map(Point::from).collect_vec();
+    let points = point_data
+        .lines()
+        .map(|line| parse_point(line.trim()))
+        .collect::<ParseResult<_>>()?;
 
     let folds = fold_data
-        .trim()
         .lines()
-        .map(|f| {
-            let on_axis: usize = f.split('=').collect_vec().get(1).unwrap().parse().unwrap();
-            if f.contains("y=") {
-                Up(on_axis)
+        .map(|line| {
+            let line = line.trim();
+            if let Some(on_axis) = line.strip_prefix("fold along y=") {
+                Ok(Up(on_axis.parse()?))
+            } else if let Some(on_axis) = line.strip_prefix("fold along x=") {
+                Ok(Left(on_axis.parse()?))
             } else {
-                Left(on_axis)
+                Err(line.error(format!("expected a fold, found `{}`", line.text)))
             }
         })
-        .collect_vec();
+        .collect::<ParseResult<_>>()?;
 
-    (points, folds)
+    Ok((points, folds))
 }
 
 #[derive(Debug)]
-enum Fold {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Fold {
     Up(usize),
     Left(usize),
 }
 
+impl Fold {
+    fn translate(&self, point: &Point) -> Point {
+        match self {
+            Up(y_coord) => {
+                if point.y > *y_coord {
+                    let offset = (point.y - y_coord) * 2;
+                    Point::new(point.x, point.y - offset)
+                } else {
+                    *point
+                }
+            }
+            Left(x_coord) => {
+                if point.x > *x_coord {
+                    let offset = (point.x - x_coord) * 2;
+                    Point::new(point.x - offset, point.y)
+                } else {
+                    *point
+                }
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::days::day13::Day13;
+    use crate::days::image::Rgb;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "6,10
+    const SAMPLE_1: &str = "6,10
 0,14
 9,10
 0,3
@@ -161,4 +178,13 @@ fold along x=5";
     fn test_sample1_part1() {
         assert_eq!(17.to_string(), Day13 {}.part1(SAMPLE_1));
     }
+
+    #[test]
+    fn test_sample1_image() {
+        // the code in the sample is a hollow square
+        let image = Day13 {}.to_image(SAMPLE_1, None).unwrap();
+        assert_eq!((5, 5), (image.width, image.height));
+        let filled = image.pixels.iter().filter(|&&p| p == Rgb::BLACK).count();
+        assert_eq!(16, filled);
+    }
 }
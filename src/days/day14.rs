@@ -1,79 +1,207 @@
+use crate::days::counter::Counter;
+use crate::days::matrix::{ln_sum, Matrix};
+use crate::days::parse::{self, ParseResult, Span};
+use crate::days::simulation::Simulation;
 use crate::Solution;
-use itertools::{Itertools, MinMaxResult};
+use itertools::Itertools;
 use std::collections::HashMap;
 
 pub struct Day14 {}
 
 impl Solution for Day14 {
     fn part1(&self, input: &str) -> String {
-        let polymer_formula = parse_input(input);
+        let polymer_formula = parse_input(input).expect("Invalid input");
 
-        let formula_result = (0..10).fold(polymer_formula.template, |acc, _i| {
-            apply_insertions(&acc, &polymer_formula.insertion_rules)
-        });
+        let mut polymerization = Polymerization {
+            polymer: polymer_formula.template,
+            insertion_rules: &polymer_formula.insertion_rules,
+        };
+        polymerization.run_for(10);
 
-        let char_counts = formula_result.chars().fold(HashMap::new(), |mut map, ch| {
-            *map.entry(ch).or_insert(0) += 1;
-            map
-        });
+        let char_counts: Counter<char> = polymerization.polymer.chars().collect();
 
-        let (min_ch_count, max_ch_count) = match char_counts.values().into_iter().minmax() {
-            MinMaxResult::MinMax(min, max) => (min, max),
+        let (min_ch_count, max_ch_count) = match (char_counts.min_count(), char_counts.max_count())
+        {
+            (Some(min), Some(max)) => (min, max),
             _ => panic!("Something went wrong..."),
         };
         (max_ch_count - min_ch_count).to_string()
     }
 
     fn part2(&self, input: &str) -> String {
-        let polymer_formula = parse_input(input);
+        let polymer_formula = parse_input(input).expect("Invalid input");
+        element_count_range(&polymer_formula, 40)
+            .expect("Too many elements to count")
+            .to_string()
+    }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self, input: &str) -> Option<serde_json::Value> {
+        let polymer_formula = parse_input(input).expect("Invalid input");
+        Some(serde_json::json!(polymer_formula))
+    }
+}
+
+/// The most common minus the least common element count after `steps` insertion steps, or `None`
+/// if a count doesn't fit in a u128.
+pub fn element_count_range(polymer_formula: &PolymerFormula, steps: u64) -> Option<u128> {
+    PairRecurrence::new(polymer_formula).count_range(steps)
+}
+
+/// How many of each element there are after `steps` insertion steps, modulo `modulus`, which
+/// must be from 1 to 2^127 so that two counts below it can be added without overflowing.
+pub fn element_counts_mod(
+    polymer_formula: &PolymerFormula,
+    steps: u64,
+    modulus: u128,
+) -> HashMap<char, u128> {
+    PairRecurrence::new(polymer_formula).element_counts_mod(steps, modulus)
+}
 
-        let initial_chars = polymer_formula.template.chars().collect_vec();
-        let initial_pairs = initial_chars
+/// [`element_count_range`] for any number of steps, modulo `modulus` (from 1 to 2^127). Once the
+/// counts no longer fit in a u128, the most and least common elements are picked by comparing the
+/// logarithms of their counts, and `None` is returned if either is too close to the next most or
+/// least common element to be told apart through floating-point rounding.
+pub fn element_count_range_mod(
+    polymer_formula: &PolymerFormula,
+    steps: u64,
+    modulus: u128,
+) -> Option<u128> {
+    let recurrence = PairRecurrence::new(polymer_formula);
+    if let Some(range) = recurrence.count_range(steps) {
+        return Some(range % modulus);
+    }
+
+    let by_size = recurrence.ln_element_counts(steps);
+    let distinct = |(_, smaller): &(char, f64), (_, larger): &(char, f64)| {
+        larger - smaller > LN_TOLERANCE * larger.abs().max(1.0)
+    };
+    let (least, most) = (by_size.first()?, by_size.last()?);
+    let n = by_size.len();
+    if n > 1 && !(distinct(least, &by_size[1]) && distinct(&by_size[n - 2], most)) {
+        return None;
+    }
+
+    let counts = recurrence.element_counts_mod(steps, modulus);
+    Some((counts[&most.0] + modulus - counts[&least.0]) % modulus)
+}
+
+/// How far apart, relative to their size, two logarithms of counts must be to be sure which count
+/// is larger. Their rounding errors are many orders of magnitude smaller.
+const LN_TOLERANCE: f64 = 1e-9;
+
+/// The insertion steps as a linear recurrence over pair counts: a pair `AB` with rule `AB -> C`
+/// becomes `AC` and `CB`. Every element is the first of exactly one pair except the last element
+/// of the template, which never changes.
+struct PairRecurrence {
+    pairs: Vec<(char, char)>,
+    transitions: Matrix,
+    /// How many of each pair the template starts with.
+    counts: Vec<u128>,
+    last: Option<char>,
+}
+
+impl PairRecurrence {
+    fn new(polymer_formula: &PolymerFormula) -> PairRecurrence {
+        let template = polymer_formula.template.chars().collect_vec();
+
+        let mut pairs: Vec<(char, char)> = template.iter().copied().tuple_windows().collect();
+        for (seq, &inserted) in &polymer_formula.insertion_rules {
+            let (first, second) = seq.chars().next_tuple().unwrap();
+            pairs.extend([(first, second), (first, inserted), (inserted, second)]);
+        }
+        let pairs = pairs.into_iter().unique().collect_vec();
+        let pair_index: HashMap<(char, char), usize> = pairs
             .iter()
-            .zip(initial_chars[1..].iter())
-            .collect_vec();
+            .enumerate()
+            .map(|(i, &pair)| (pair, i))
+            .collect();
 
-        // number of occurrences of each 2-character sequence
-        let mut seq_map: HashMap<String, usize> =
-            initial_pairs.iter().fold(HashMap::new(), |mut map, &pair| {
-                *map.entry(format!("{}{}", *pair.0, *pair.1)).or_insert(0) += 1;
-                map
-            });
-        // number of occurrences of each character
-        let mut char_counts: HashMap<char, usize> =
-            initial_chars.iter().fold(HashMap::new(), |mut map, ch| {
-                *map.entry(*ch).or_insert(0) += 1;
-                map
-            });
-
-        for _ in 0..40 {
-            let seq_and_count: Vec<(String, usize)> = seq_map
-                .iter()
-                .map(|(k, v)| (k.to_string(), *v))
-                .collect_vec();
-
-            let mut cur_seq_map: HashMap<String, usize> = HashMap::new();
-            for (seq, count) in seq_and_count {
-                // char count increases for each rule match
-                let inserted_ch = polymer_formula.insertion_rules.get(&seq).unwrap();
-                *char_counts.entry(*inserted_ch).or_insert(0) += count;
-
-                // sequence count increases according to the would-be character pairs
-                let new_seq1: String = format!("{}{}", seq.chars().next().unwrap(), inserted_ch);
-                let new_seq2: String = format!("{}{}", inserted_ch, seq.chars().nth(1).unwrap());
-                *cur_seq_map.entry(new_seq1).or_insert(0) += count;
-                *cur_seq_map.entry(new_seq2).or_insert(0) += count;
+        let mut transitions = Matrix::zero(pairs.len());
+        for (i, &(first, second)) in pairs.iter().enumerate() {
+            match polymer_formula
+                .insertion_rules
+                .get(&format!("{}{}", first, second))
+            {
+                Some(&inserted) => {
+                    transitions.add_transition(i, pair_index[&(first, inserted)], 1);
+                    transitions.add_transition(i, pair_index[&(inserted, second)], 1);
+                }
+                // pairs without a rule are left alone
+                None => transitions.add_transition(i, i, 1),
             }
+        }
 
-            seq_map = cur_seq_map;
+        let mut counts = vec![0; pairs.len()];
+        for pair in template.iter().copied().tuple_windows() {
+            counts[pair_index[&pair]] += 1;
         }
 
-        let (min_ch_count, max_ch_count) = match char_counts.values().into_iter().minmax() {
-            MinMaxResult::MinMax(min, max) => (min, max),
-            _ => panic!("Something went wrong..."),
-        };
+        PairRecurrence {
+            pairs,
+            transitions,
+            counts,
+            last: template.last().copied(),
+        }
+    }
 
-        (max_ch_count - min_ch_count).to_string()
+    /// The most common minus the least common element count after `steps` steps, or `None` if a
+    /// count doesn't fit in a u128.
+    fn count_range(&self, steps: u64) -> Option<u128> {
+        let pair_counts = self.transitions.checked_counts_after(&self.counts, steps)?;
+        let char_counts = self.element_counts(&pair_counts)?;
+        Some(char_counts.max_count()? - char_counts.min_count()?)
+    }
+
+    fn element_counts_mod(&self, steps: u64, modulus: u128) -> HashMap<char, u128> {
+        assert!(
+            (1..=1 << 127).contains(&modulus),
+            "Modulus must be from 1 to 2^127"
+        );
+        let pair_counts = self
+            .transitions
+            .counts_after_mod(&self.counts, steps, modulus);
+
+        let mut char_counts: HashMap<char, u128> = HashMap::new();
+        let firsts = self.pairs.iter().map(|&(first, _)| first);
+        for (ch, count) in firsts
+            .zip(pair_counts)
+            .chain(self.last.map(|last| (last, 1)))
+        {
+            let total = char_counts.entry(ch).or_default();
+            *total = (*total + count) % modulus;
+        }
+        char_counts
+    }
+
+    /// The natural logarithm of each element's count after `steps` steps, smallest first.
+    fn ln_element_counts(&self, steps: u64) -> Vec<(char, f64)> {
+        let ln_pair_counts = self.transitions.ln_counts_after(&self.counts, steps);
+        let mut ln_terms: HashMap<char, Vec<f64>> = HashMap::new();
+        let firsts = self.pairs.iter().map(|&(first, _)| first);
+        for (ch, ln_count) in firsts
+            .zip(ln_pair_counts)
+            .chain(self.last.map(|last| (last, 0.0)))
+        {
+            ln_terms.entry(ch).or_default().push(ln_count);
+        }
+        ln_terms
+            .into_iter()
+            .map(|(ch, terms)| (ch, ln_sum(terms.into_iter())))
+            .sorted_by(|a, b| a.1.total_cmp(&b.1))
+            .collect()
+    }
+
+    /// The element counts given the count of each pair, or `None` if one doesn't fit in a u128.
+    fn element_counts(&self, pair_counts: &[u128]) -> Option<Counter<char, u128>> {
+        let mut char_counts: Counter<char, u128> = Counter::new();
+        for (&(first, _), &count) in self.pairs.iter().zip(pair_counts) {
+            char_counts.try_add_n(first, count)?;
+        }
+        if let Some(last) = self.last {
+            char_counts.try_add_n(last, 1)?;
+        }
+        Some(char_counts)
     }
 }
 
@@ -98,36 +226,63 @@ fn apply_insertions(template: &str, rules: &HashMap<String, char>) -> String {
     result
 }
 
-fn parse_input(input: &str) -> PolymerFormula {
-    let split = input.trim().split("\n\n").collect_vec();
-    let template = split.get(0).unwrap().to_string();
+fn parse_input(input: &str) -> ParseResult<PolymerFormula> {
+    let (template, rules) = match *parse::blocks(Span::new(input)).as_slice() {
+        [template, rules] => (template, rules),
+        _ => {
+            return Err(Span::new(input).error("expected a template and a block of insertion rules"))
+        }
+    };
 
-    let insertion_rules: HashMap<String, char> = split
-        .get(1)
-        .unwrap()
-        .lines()
-        .map(|line| line.split_once(" -> ").unwrap())
-        .map(|it| (it.0.to_string(), it.1.chars().next().unwrap()))
-        .collect();
+    let insertion_rules: HashMap<String, char> = parse::rule_lines(rules)?
+        .into_iter()
+        .map(
+            |(pair, inserted)| match *inserted.text.chars().collect_vec().as_slice() {
+                [ch] if pair.text.chars().count() == 2 => Ok((pair.text.to_string(), ch)),
+                _ => Err(pair.error("expected a rule like `CH -> B`")),
+            },
+        )
+        .collect::<ParseResult<_>>()?;
 
-    PolymerFormula {
-        template,
+    Ok(PolymerFormula {
+        template: template.text.to_string(),
         insertion_rules,
-    }
+    })
 }
 
 #[derive(Debug)]
-struct PolymerFormula {
-    template: String,
-    insertion_rules: HashMap<String, char>,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PolymerFormula {
+    pub template: String,
+    pub insertion_rules: HashMap<String, char>,
+}
+
+/// The polymer built out from a template, one round of insertions per step.
+struct Polymerization<'a> {
+    polymer: String,
+    insertion_rules: &'a HashMap<String, char>,
+}
+
+impl Simulation for Polymerization<'_> {
+    type Output = ();
+
+    fn step(&mut self) {
+        self.polymer = apply_insertions(&self.polymer, self.insertion_rules);
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day14::Day14;
+    use crate::days::day14::{
+        element_count_range, element_count_range_mod, element_counts_mod, parse_input, Day14,
+        PairRecurrence, PolymerFormula,
+    };
     use crate::Solution;
+    use itertools::Itertools;
+    use num_bigint::BigUint;
+    use std::collections::HashMap;
 
-    const SAMPLE_1: &'static str = "NNCB
+    const SAMPLE_1: &str = "NNCB
 
 CH -> B
 HH -> N
@@ -155,4 +310,81 @@ CN -> C";
     fn test_sample1_part2() {
         assert_eq!(2188189693529usize.to_string(), Day14 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_sample1_matches_simulation() {
+        let formula = parse_input(SAMPLE_1).unwrap();
+        assert_eq!(Some(1588), element_count_range(&formula, 10));
+        // the polymer doubles in length each step, so its counts stop fitting after about 126
+        assert!(element_count_range(&formula, 120).is_some());
+        assert_eq!(None, element_count_range(&formula, 130));
+    }
+
+    /// Each element's exact count after `steps` steps, least common first.
+    fn exact_counts(formula: &PolymerFormula, steps: u64) -> Vec<(char, BigUint)> {
+        let recurrence = PairRecurrence::new(formula);
+        let counts = recurrence
+            .counts
+            .iter()
+            .map(|&c| BigUint::from(c))
+            .collect_vec();
+        let pair_counts = recurrence.transitions.big_counts_after(&counts, steps);
+        let mut char_counts: HashMap<char, BigUint> = HashMap::new();
+        let firsts = recurrence.pairs.iter().map(|&(first, _)| first);
+        for (ch, count) in firsts
+            .zip(pair_counts)
+            .chain(recurrence.last.map(|last| (last, BigUint::from(1u32))))
+        {
+            *char_counts.entry(ch).or_default() += count;
+        }
+        char_counts
+            .into_iter()
+            .sorted_by(|a, b| a.1.cmp(&b.1))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample1_beyond_u128() {
+        let formula = parse_input(SAMPLE_1).unwrap();
+        let modulus = 1_000_000_007;
+        for steps in [10, 40, 120, 200, 1000] {
+            let counts = exact_counts(&formula, steps);
+            let range = &counts[counts.len() - 1].1 - &counts[0].1;
+            assert_eq!(
+                Some(range % modulus),
+                element_count_range_mod(&formula, steps, modulus).map(BigUint::from)
+            );
+        }
+    }
+
+    #[test]
+    fn test_sample1_millions_of_steps() {
+        let formula = parse_input(SAMPLE_1).unwrap();
+        let modulus = 1_000_000_007;
+
+        // after n steps the 4-element template has grown to 3 * 2^n + 1 elements
+        let steps = 1_000_000;
+        let length = (0..steps).fold(3, |length, _| length * 2 % modulus) + 1;
+        let counts = element_counts_mod(&formula, steps, modulus);
+        assert_eq!(length % modulus, counts.values().sum::<u128>() % modulus);
+
+        // the share of each element settles down long before, so the exact order at 1000 steps
+        // still holds
+        let order = exact_counts(&formula, 1000);
+        let (least, most) = (order[0].0, order[order.len() - 1].0);
+        assert_eq!(
+            Some((counts[&most] + modulus - counts[&least]) % modulus),
+            element_count_range_mod(&formula, steps, modulus)
+        );
+    }
+
+    #[test]
+    fn test_near_ties_are_not_guessed() {
+        // the polymer alternates like the Thue-Morse sequence, so A and B stay within a few of
+        // each other
+        let formula = parse_input("AB\n\nAB -> B\nBB -> A\nBA -> A\nAA -> B").unwrap();
+        let modulus = 1_000_000_007;
+        assert!(element_count_range_mod(&formula, 10, modulus).is_some());
+        assert_eq!(None, element_count_range_mod(&formula, 200, modulus));
+    }
 }
@@ -1,13 +1,15 @@
+use crate::days::geometry;
+use crate::days::image::{Image, Palette, Rgb};
+use crate::days::parse::{self, Span};
+use crate::days::search;
 use crate::Solution;
-use std::cmp::Ordering;
-use std::collections::{BinaryHeap, HashMap};
 
 pub struct Day15 {}
 
 impl Solution for Day15 {
     fn part1(&self, input: &str) -> String {
         let grid: Grid<usize> = parse_input(input);
-        let goal_point = (grid.width - 1, grid.height - 1);
+        let goal_point = Point::new(grid.width - 1, grid.height - 1);
 
         lowest_risk_to_goal(&grid, START_POINT, goal_point)
             .map(|it| it.to_string())
@@ -41,82 +43,48 @@ impl Solution for Day15 {
             width: prototype_grid.width * 5,
             values: full_values,
         };
-        let goal_point = (grid.width - 1, grid.height - 1);
+        let goal_point = Point::new(grid.width - 1, grid.height - 1);
 
         lowest_risk_to_goal(&grid, START_POINT, goal_point)
             .map(|it| it.to_string())
             .unwrap_or_else(|| "No solution".to_string())
     }
-}
-
-fn parse_input(input: &str) -> Grid<usize> {
-    Grid::from(input.trim(), |ch| ch.to_digit(10).unwrap() as usize)
-}
-
-type Point = (usize, usize);
-const START_POINT: Point = (0, 0);
-
-/// https://doc.rust-lang.org/std/collections/binary_heap/index.html#examples
-fn lowest_risk_to_goal(g: &Grid<usize>, start: Point, goal: Point) -> Option<i32> {
-    let mut heap: BinaryHeap<State> = BinaryHeap::new();
-    let mut total_risk: HashMap<Point, i32> = HashMap::new();
-
-    total_risk.insert(start, 0);
-    heap.push(State {
-        risk: 0,
-        point: start,
-    });
 
-    let lookup_risk =
-        |point: &Point, risk_map: &HashMap<Point, i32>| *risk_map.get(point).unwrap_or(&i32::MAX);
-
-    while let Some(State { risk, point }) = heap.pop() {
-        if point == goal {
-            return Some(risk);
-        } else if risk > lookup_risk(&point, &total_risk) {
-            continue;
-        } else {
-            let (x, y) = point;
-            for (nx, ny) in g.adjacent_cells(x, y) {
-                let neighbor_point = (nx, ny);
-
-                let risk_through_here = risk + *g.value_at(nx, ny).unwrap() as i32;
-                let existing_risk = lookup_risk(&neighbor_point, &total_risk);
-
-                if risk_through_here < existing_risk {
-                    let next_state = State {
-                        risk: risk_through_here,
-                        point: neighbor_point,
-                    };
-                    heap.push(next_state);
-                    total_risk.insert(neighbor_point, next_state.risk);
-                }
-            }
-        }
+    fn to_image(&self, input: &str, palette: Option<&Palette>) -> Option<Image> {
+        let grid: Grid<usize> = parse_input(input);
+        let risks = grid.values.iter().map(|&it| it as u64);
+        let default = Palette::Gradient(Rgb(26, 152, 80), Rgb(215, 48, 39));
+        let palette = palette.unwrap_or(&default);
+        Some(Image::from_grid(grid.width, grid.height, risks, palette))
     }
-
-    None
-}
-
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
-struct State {
-    risk: i32,
-    point: Point,
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> Ordering {
-        other
-            .risk
-            .cmp(&self.risk)
-            .then_with(|| self.point.cmp(&other.point))
+fn parse_input(input: &str) -> Grid<usize> {
+    let digits = parse::digit_grid(Span::new(input)).expect("Invalid input");
+    Grid {
+        values: digits.values.iter().map(|&it| it as usize).collect(),
+        width: digits.width,
+        height: digits.height,
     }
 }
 
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.cmp(other))
-    }
+type Point = geometry::Point<usize>;
+const START_POINT: Point = Point { x: 0, y: 0 };
+
+/// The lowest total risk of the cells entered on the way from `start` to `goal`. Every cell
+/// costs at least 1, so the Manhattan distance to the goal never overestimates what's left.
+fn lowest_risk_to_goal(g: &Grid<usize>, start: Point, goal: Point) -> Option<usize> {
+    search::astar(
+        start,
+        |point: &Point| {
+            g.adjacent_cells(point)
+                .into_iter()
+                .map(|adj| (adj, *g.value_at(adj.x, adj.y).unwrap()))
+        },
+        |point| point.manhattan_distance(&goal),
+        |point| *point == goal,
+    )
+    .map(|result| result.cost)
 }
 
 #[derive(Debug)]
@@ -127,58 +95,17 @@ struct Grid<T> {
 }
 
 impl<T> Grid<T> {
-    fn from(str: &str, char_to_t: fn(char) -> T) -> Grid<T> {
-        let trimmed = str.trim();
-        let width = trimmed.lines().next().unwrap().len();
-        let height = trimmed.lines().count();
-
-        let values = trimmed
-            .lines()
-            .flat_map(|line| line.chars())
-            .map(char_to_t)
-            .collect();
-        Grid {
-            values,
-            width,
-            height,
-        }
-    }
-
     fn value_at(&self, x: usize, y: usize) -> Option<&T> {
         let index: usize = x + y * self.width;
         self.values.get(index)
     }
 
-    fn adjacent_cells(&self, x: usize, y: usize) -> Vec<Point> {
-        fn value_at_internal<T>(
-            width: usize,
-            height: usize,
-            values: &[T],
-            x: isize,
-            y: isize,
-        ) -> Option<&T> {
-            if !(0..width).contains(&usize::try_from(x).unwrap_or(width + 1))
-                || !(0..height).contains(&usize::try_from(y).unwrap_or(height + 1))
-            {
-                None
-            } else {
-                let index: usize = (x + y * width as isize) as usize;
-                values.get(index)
-            }
-        }
-
-        let xi = isize::try_from(x).unwrap();
-        let yi = isize::try_from(y).unwrap();
-        let possible_adj_cells: Vec<(isize, isize)> =
-            vec![(xi - 1, yi), (xi + 1, yi), (xi, yi + 1), (xi, yi - 1)];
-
-        possible_adj_cells
+    fn adjacent_cells(&self, point: &Point) -> Vec<Point> {
+        let signed = point.try_convert::<isize>().unwrap();
+        geometry::Point::orthogonal_directions()
             .iter()
-            .filter_map(|(x1, y1)| {
-                value_at_internal(self.width, self.height, &self.values, *x1, *y1)
-                    .map(|_| (*x1, *y1))
-            })
-            .map(|(x1, y1)| (usize::try_from(x1).unwrap(), usize::try_from(y1).unwrap()))
+            .filter_map(|&step| (signed + step).try_convert::<usize>())
+            .filter(|adj| adj.x < self.width && adj.y < self.height)
             .collect()
     }
 }
@@ -188,7 +115,7 @@ mod tests {
     use crate::days::day15::Day15;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "1163751742
+    const SAMPLE_1: &str = "1163751742
 1381373672
 2136511328
 3694931569
@@ -199,7 +126,7 @@ mod tests {
 1293138521
 2311944581";
 
-    const SAMPLE_1_FULL: &'static str = "11637517422274862853338597396444961841755517295286
+    const SAMPLE_1_FULL: &str = "11637517422274862853338597396444961841755517295286
 13813736722492484783351359589446246169155735727126
 21365113283247622439435873354154698446526571955763
 36949315694715142671582625378269373648937148475914
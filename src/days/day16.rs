@@ -1,213 +1,314 @@
+use crate::days::parse::{ParseResult, Span};
 use crate::Solution;
-use itertools::Itertools;
 use log::debug;
 use std::cmp::Ordering;
+use std::fmt;
 
 pub struct Day16 {}
 
 impl Solution for Day16 {
     fn part1(&self, input: &str) -> String {
-        let bin = parse_input_as_bin_str(input);
+        let (bytes, bit_len) = parse_input_as_bytes(input).expect("Invalid input");
+        let mut reader = BitReader::new(&bytes, bit_len);
 
-        let mut pos = 0;
         let mut version_sum = 0;
-        while let Some((version, next_pos)) = read_version_shallow(&bin, pos) {
+        while let Some(version) = read_version_shallow(&mut reader) {
             version_sum += version;
-            pos = next_pos;
         }
 
         version_sum.to_string()
     }
 
     fn part2(&self, input: &str) -> String {
-        let bin = parse_input_as_bin_str(input);
-        let (result, _pos) = eval_packet(&bin, 0).expect("Bad packet");
-        result.to_string()
+        evaluate(input).expect("Invalid input").to_string()
     }
 }
 
-fn parse_input_as_bin_str(input: &str) -> Vec<char> {
-    input
-        .trim()
-        .chars()
-        .map(|ch| u32::from_str_radix(&*format!("{}", ch), 16).unwrap())
-        .map(|it| format!("{:01$b}", it, 4))
-        .join("")
-        .chars()
-        .collect_vec()
+/// Evaluates the transmission's outermost packet. A packet that can't be evaluated is reported at
+/// the hex digit holding its first bit.
+fn evaluate(input: &str) -> ParseResult<u128> {
+    let span = Span::new(input).trim();
+    let (bytes, bit_len) = parse_input_as_bytes(input)?;
+    eval_packet(&mut BitReader::new(&bytes, bit_len)).map_err(|e| {
+        let digit = e.packet() / 4;
+        span.slice(digit, digit + 1).error(e.to_string())
+    })
+}
+
+/// Packs each pair of hex digits into a byte (an odd trailing digit fills the high half) and
+/// returns the bytes along with the number of bits they hold.
+fn parse_input_as_bytes(input: &str) -> ParseResult<(Vec<u8>, usize)> {
+    let span = Span::new(input).trim();
+    let mut nibbles = Vec::with_capacity(span.text.len());
+    for (i, ch) in span.text.chars().enumerate() {
+        match ch.to_digit(16) {
+            Some(nibble) => nibbles.push(nibble as u8),
+            None => {
+                let at = Span {
+                    column: span.column + i,
+                    ..span
+                };
+                return Err(at.error(format!("expected a hex digit, found `{}`", ch)));
+            }
+        }
+    }
+
+    let bytes = nibbles
+        .chunks(2)
+        .map(|pair| (pair[0] << 4) | pair.get(1).copied().unwrap_or(0))
+        .collect();
+    Ok((bytes, nibbles.len() * 4))
+}
+
+/// Reads big-endian bit fields of up to 64 bits from a byte slice.
+struct BitReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(bytes: &'a [u8], bit_len: usize) -> BitReader<'a> {
+        BitReader {
+            bytes,
+            pos: 0,
+            end: usize::min(bit_len, bytes.len() * 8),
+        }
+    }
+
+    /// Position of the next bit to read, counted from the start of the underlying bytes.
+    fn position(&self) -> usize {
+        self.pos
+    }
+
+    fn remaining(&self) -> usize {
+        self.end - self.pos
+    }
+
+    fn read_bits(&mut self, num_bits: usize) -> Option<u64> {
+        if num_bits > 64 || num_bits > self.remaining() {
+            return None;
+        }
+
+        let mut value: u64 = 0;
+        let mut left = num_bits;
+        while left > 0 {
+            let byte = self.bytes[self.pos / 8];
+            let bit_offset = self.pos % 8;
+            let take = usize::min(8 - bit_offset, left);
+            let bits = (byte >> (8 - bit_offset - take)) & (0xff >> (8 - take));
+
+            value = (value << take) | bits as u64;
+            self.pos += take;
+            left -= take;
+        }
+
+        Some(value)
+    }
+
+    fn read_bit(&mut self) -> Option<bool> {
+        self.read_bits(1).map(|bit| bit == 1)
+    }
+
+    /// A reader over the next `num_bits` bits; this reader skips past them.
+    fn sub_reader(&mut self, num_bits: usize) -> Option<BitReader<'a>> {
+        if num_bits > self.remaining() {
+            return None;
+        }
+
+        let sub = BitReader {
+            bytes: self.bytes,
+            pos: self.pos,
+            end: self.pos + num_bits,
+        };
+        self.pos += num_bits;
+        Some(sub)
+    }
 }
 
 // a LiteralValue packet w/ value encoded in 5 bits
 const MIN_PACKET_BITS: usize = 11;
 
-fn read_version_shallow(bin: &[char], start_pos: usize) -> Option<(usize, usize)> {
-    if start_pos + MIN_PACKET_BITS > bin.len() {
+fn read_version_shallow(reader: &mut BitReader) -> Option<u64> {
+    if reader.remaining() < MIN_PACKET_BITS {
         return None;
     }
 
-    let mut pos = start_pos;
-
-    let version = consume_bits_as_usize(bin, &mut pos, 3);
-    let type_id = consume_bits_as_usize(bin, &mut pos, 3);
+    let version = reader.read_bits(3)?;
+    let type_id = reader.read_bits(3)?;
 
     match type_id {
         4 => {
             // literal value
-            let mut literal = String::from("");
-
-            let mut group = consume_bits_as_char_slice(bin, &mut pos, 5);
-            literal.push_str(&*group[1..].iter().join(""));
-
-            while group[0] == '1' {
-                group = consume_bits_as_char_slice(bin, &mut pos, 5);
-                literal.push_str(&*group[1..].iter().join(""));
-            }
+            skip_literal(reader)?;
         }
         _ => {
             // operator
-            let length_type_id = consume_bits_as_char_slice(bin, &mut pos, 1);
-            match *length_type_id {
-                ['0'] => {
-                    consume_bits_as_usize(bin, &mut pos, 15);
-                }
-                _ => {
-                    consume_bits_as_usize(bin, &mut pos, 11);
-                }
+            let num_length_bits = if reader.read_bit()? { 11 } else { 15 };
+            reader.read_bits(num_length_bits)?;
+        }
+    }
+
+    Some(version)
+}
+
+fn skip_literal(reader: &mut BitReader) -> Option<()> {
+    loop {
+        let more = reader.read_bit()?;
+        reader.read_bits(4)?;
+        if !more {
+            return Some(());
+        }
+    }
+}
+
+/// Why a packet couldn't be evaluated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PacketError {
+    /// The bits ran out, or an operator has the wrong number of sub-packets.
+    Malformed { packet: usize },
+    /// A literal or computed value doesn't fit in a u128.
+    Overflow { packet: usize },
+}
+
+impl PacketError {
+    /// The position of the first bit of the packet at fault.
+    fn packet(&self) -> usize {
+        match *self {
+            PacketError::Malformed { packet } | PacketError::Overflow { packet } => packet,
+        }
+    }
+}
+
+impl fmt::Display for PacketError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PacketError::Malformed { packet } => write!(f, "malformed packet at bit {}", packet),
+            PacketError::Overflow { packet } => {
+                write!(f, "value of packet at bit {} doesn't fit in a u128", packet)
             }
         }
     }
+}
 
-    Some((version, pos))
+/// Reads a whole literal, even one too big to return, or `None` if the bits run out.
+fn read_literal(reader: &mut BitReader) -> Option<Option<u128>> {
+    let mut literal: Option<u128> = Some(0);
+    loop {
+        let more = reader.read_bit()?;
+        let group = reader.read_bits(4)? as u128;
+        literal = literal
+            .and_then(|it| it.checked_mul(16))
+            .map(|it| it | group);
+        if !more {
+            return Some(literal);
+        }
+    }
 }
 
-fn eval_packet(bin: &[char], start_pos: usize) -> Option<(usize, usize)> {
-    if start_pos + MIN_PACKET_BITS > bin.len() {
+fn eval_packet(reader: &mut BitReader) -> Result<u128, PacketError> {
+    let packet = reader.position();
+    let malformed = PacketError::Malformed { packet };
+    let overflow = PacketError::Overflow { packet };
+    if reader.remaining() < MIN_PACKET_BITS {
         debug!(
-            "No packets remaining at pos={}, len={}",
-            start_pos,
-            bin.len()
+            "No packets remaining at pos={}, remaining={}",
+            reader.position(),
+            reader.remaining()
         );
-        return None;
+        return Err(malformed);
     }
+    let read_bits = |reader: &mut BitReader, num_bits| reader.read_bits(num_bits).ok_or(malformed);
 
-    let mut pos = start_pos;
-
-    let version = consume_bits_as_usize(bin, &mut pos, 3);
-    let type_id = consume_bits_as_usize(bin, &mut pos, 3);
+    let version = read_bits(reader, 3)?;
+    let type_id = read_bits(reader, 3)?;
     debug!("Packet v{} ({})", version, type_id);
 
-    let result: usize = match type_id {
+    let result: u128 = match type_id {
         4 => {
             // literal value
-            let mut literal = String::from("");
-
-            let mut group = consume_bits_as_char_slice(bin, &mut pos, 5);
-            literal.push_str(&*group[1..].iter().join(""));
-
-            while group[0] == '1' {
-                group = consume_bits_as_char_slice(bin, &mut pos, 5);
-                literal.push_str(&*group[1..].iter().join(""));
-            }
-
-            let r = usize::from_str_radix(&*literal, 2).unwrap();
+            let r = read_literal(reader).ok_or(malformed)?.ok_or(overflow)?;
             debug!("Literal {}", r);
             r
         }
         _ => {
             // operator
-            let length_type_id = consume_bits_as_char_slice(bin, &mut pos, 1);
-            let mut sub_packet_results: Vec<usize> = Vec::new();
-            match *length_type_id {
-                ['0'] => {
-                    let num_sub_bits = consume_bits_as_usize(bin, &mut pos, 15);
-                    debug!("Operator ({} sub-bits)", num_sub_bits);
-
-                    let end = pos + num_sub_bits;
-                    while pos < end {
-                        let eval = eval_packet(&bin[pos..], 0);
-                        sub_packet_results.push(eval.unwrap().0);
-                        pos += eval.unwrap().1;
-                    }
+            let mut sub_packet_results: Vec<u128> = Vec::new();
+            if read_bits(reader, 1)? == 1 {
+                let num_sub_packets = read_bits(reader, 11)?;
+                debug!("Operator ({} sub-packets)", num_sub_packets);
+
+                for _p in 0..num_sub_packets {
+                    sub_packet_results.push(eval_packet(reader)?);
                 }
-                _ => {
-                    let num_sub_packets = consume_bits_as_usize(bin, &mut pos, 11);
-                    debug!("Operator ({} sub-packets)", num_sub_packets);
-
-                    for _p in 0..num_sub_packets {
-                        let eval = eval_packet(&bin[pos..], 0);
-                        sub_packet_results.push(eval.unwrap().0);
-                        pos += eval.unwrap().1;
-                    }
+            } else {
+                let num_sub_bits = read_bits(reader, 15)? as usize;
+                debug!("Operator ({} sub-bits)", num_sub_bits);
+
+                let mut sub_reader = reader.sub_reader(num_sub_bits).ok_or(malformed)?;
+                while sub_reader.remaining() > 0 {
+                    sub_packet_results.push(eval_packet(&mut sub_reader)?);
                 }
             }
 
             match type_id {
                 0 => {
-                    let r = sub_packet_results.iter().sum();
+                    let r = sub_packet_results
+                        .iter()
+                        .try_fold(0u128, |acc, &it| acc.checked_add(it))
+                        .ok_or(overflow)?;
                     debug!("SUM({:?}) = {}", sub_packet_results, r);
                     r
                 }
                 1 => {
-                    let r = sub_packet_results.iter().product();
+                    let r = sub_packet_results
+                        .iter()
+                        .try_fold(1u128, |acc, &it| acc.checked_mul(it))
+                        .ok_or(overflow)?;
                     debug!("PRODUCT({:?}) = {}", sub_packet_results, r);
                     r
                 }
                 2 => {
-                    let r = *sub_packet_results.iter().min().unwrap();
+                    let r = *sub_packet_results.iter().min().ok_or(malformed)?;
                     debug!("MIN({:?}) = {}", sub_packet_results, r);
                     r
                 }
                 3 => {
-                    let r = *sub_packet_results.iter().max().unwrap();
+                    let r = *sub_packet_results.iter().max().ok_or(malformed)?;
                     debug!("MAX({:?}) = {}", sub_packet_results, r);
                     r
                 }
-                5..=MIN_PACKET_BITS => {
+                5..=7 => {
                     // >, <, =
                     let (left, right) = match sub_packet_results.as_slice() {
                         [first, second] => (first, second),
-                        _ => panic!("Expected exactly 2 sub-packets for comparison"),
+                        _ => return Err(malformed),
                     };
                     let ordering = match type_id {
                         5 => Ordering::Greater,
                         6 => Ordering::Less,
                         7 => Ordering::Equal,
-                        _ => panic!("Unreachable"),
+                        _ => unreachable!(),
                     };
 
                     let r = if left.cmp(right) == ordering { 1 } else { 0 };
                     debug!("{} {:?} {}={}", left, ordering, right, r);
                     r
                 }
-                x => panic!("Unknown operator type: {}", x),
+                // type ids are 3 bits, and 4 is a literal
+                _ => unreachable!(),
             }
         }
     };
 
-    Some((result, pos))
-}
-
-fn consume_bits_as_usize(bin: &[char], start_pos: &mut usize, num_bits: usize) -> usize {
-    usize_from_slice(consume_bits_as_char_slice(bin, start_pos, num_bits))
-}
-
-fn usize_from_slice(char_slice: &[char]) -> usize {
-    usize::from_str_radix(&*String::from_iter(char_slice.iter()), 2).unwrap()
-}
-
-fn consume_bits_as_char_slice<'a>(
-    bin: &'a [char],
-    start_pos: &mut usize,
-    num_bits: usize,
-) -> &'a [char] {
-    let result = &bin[*start_pos..(*start_pos + num_bits)];
-    *start_pos += num_bits;
-    result
+    Ok(result)
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day16::Day16;
+    use crate::days::day16::{
+        eval_packet, evaluate, parse_input_as_bytes, BitReader, Day16, PacketError,
+    };
     use crate::Solution;
 
     #[test]
@@ -269,6 +370,29 @@ mod tests {
         assert_eq!(1.to_string(), Day16 {}.part2("9C0141080250320F1802104A08"));
     }
 
+    #[test]
+    fn bit_reader_crosses_byte_boundaries() {
+        let bytes = [0b1011_0011, 0b1100_0101, 0b0110_1001];
+        let mut reader = BitReader::new(&bytes, 24);
+        assert_eq!(Some(0b101), reader.read_bits(3));
+        assert_eq!(Some(0b1_0011_1100), reader.read_bits(9));
+        assert_eq!(12, reader.position());
+
+        let mut sub = reader.sub_reader(6).unwrap();
+        assert_eq!(18, reader.position());
+        assert_eq!(Some(0b01_0101), sub.read_bits(6));
+        assert_eq!(None, sub.read_bits(1));
+
+        assert_eq!(Some(0b10_1001), reader.read_bits(6));
+        assert_eq!(0, reader.remaining());
+    }
+
+    #[test]
+    fn odd_length_input() {
+        // literal 2021 followed by a single spare hex digit
+        assert_eq!(2021.to_string(), Day16 {}.part2("D2FE28F"));
+    }
+
     #[test]
     fn more_samples_part1() {
         assert_eq!(16.to_string(), Day16 {}.part1("8A004A801A8002F478"));
@@ -282,4 +406,94 @@ mod tests {
             Day16 {}.part1("A0016C880162017C3686B18A3D4780")
         );
     }
+
+    /// A literal packet (version 0) holding the value written in `hex`.
+    fn literal_bits(hex: &str) -> String {
+        let mut bits = String::from("000100");
+        for (i, ch) in hex.chars().enumerate() {
+            let more = if i + 1 < hex.len() { '1' } else { '0' };
+            bits.push(more);
+            bits.push_str(&format!("{:04b}", ch.to_digit(16).unwrap()));
+        }
+        bits
+    }
+
+    /// An operator packet (version 0) counting its sub-packets.
+    fn operator_bits(type_id: u8, sub_packets: &[String]) -> String {
+        format!(
+            "000{:03b}1{:011b}{}",
+            type_id,
+            sub_packets.len(),
+            sub_packets.concat()
+        )
+    }
+
+    fn eval_bits(bits: &str) -> Result<u128, PacketError> {
+        let (bytes, bit_len) = parse_input_as_bytes(&to_hex(bits)).unwrap();
+        eval_packet(&mut BitReader::new(&bytes, bit_len))
+    }
+
+    #[test]
+    fn values_wider_than_u64() {
+        let two_pow_64 = literal_bits("10000000000000000");
+        let two_pow_63 = literal_bits("8000000000000000");
+        assert_eq!(Ok(1 << 64), eval_bits(&two_pow_64));
+        assert_eq!(
+            Ok(1 << 127),
+            eval_bits(&operator_bits(1, &[two_pow_64.clone(), two_pow_63]))
+        );
+        assert_eq!(
+            Ok(1 << 65),
+            eval_bits(&operator_bits(0, &[two_pow_64.clone(), two_pow_64]))
+        );
+    }
+
+    #[test]
+    fn overflow_is_reported() {
+        let two_pow_64 = literal_bits("10000000000000000");
+        assert_eq!(
+            Err(PacketError::Overflow { packet: 0 }),
+            eval_bits(&operator_bits(1, &[two_pow_64.clone(), two_pow_64]))
+        );
+
+        let u128_max = literal_bits(&"F".repeat(32));
+        assert_eq!(Ok(u128::MAX), eval_bits(&u128_max));
+        assert_eq!(
+            Err(PacketError::Overflow { packet: 0 }),
+            eval_bits(&operator_bits(0, &[u128_max, literal_bits("1")]))
+        );
+        assert_eq!(
+            Err(PacketError::Overflow { packet: 0 }),
+            eval_bits(&literal_bits(&format!("1{}", "0".repeat(32))))
+        );
+        assert_eq!(
+            Err(PacketError::Malformed { packet: 0 }),
+            eval_bits("000100")
+        );
+    }
+
+    fn to_hex(bits: &str) -> String {
+        let padded = format!("{:0<width$}", bits, width = bits.len().div_ceil(8) * 8);
+        padded
+            .as_bytes()
+            .chunks(4)
+            .map(|nibble| {
+                let nibble = std::str::from_utf8(nibble).unwrap();
+                format!("{:X}", u8::from_str_radix(nibble, 2).unwrap())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn bad_packets_are_reported_where_they_start() {
+        // a sum of a literal and a comparison with only one sub-packet
+        let one = literal_bits("1");
+        let comparison = operator_bits(5, std::slice::from_ref(&one));
+        let hex = to_hex(&operator_bits(0, &[one.clone(), comparison]));
+
+        let error = evaluate(&hex).unwrap_err();
+        let start = 18 + one.len();
+        assert_eq!((1, start / 4 + 1), (error.line, error.column));
+        assert!(error.message.contains(&format!("bit {}", start)));
+    }
 }
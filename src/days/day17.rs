@@ -1,20 +1,23 @@
+use crate::days::geometry;
+use crate::days::interval::Interval;
+use crate::days::parse::{ParseResult, Span};
 use crate::Solution;
-use itertools::Itertools;
-use regex::Regex;
-use std::cmp::{max, Ordering};
+use std::cmp::max;
 use std::collections::HashSet;
 use std::ops::RangeInclusive;
 
 pub struct Day17 {}
 
+type Point = geometry::Point<isize>;
+
 impl Solution for Day17 {
     fn part1(&self, input: &str) -> String {
-        let (x_range, y_range) = parse_input(input);
+        let (x_range, y_range) = parse_input(input).expect("Invalid input");
 
         let mut max: Option<isize> = None;
         for v_x in potential_v_x(&x_range) {
             for v_y in -1000..1000 {
-                let max_y = max_y_if_hit(v_x, v_y, &x_range, &y_range);
+                let max_y = max_y_if_hit(Point::new(v_x, v_y), &x_range, &y_range);
                 max = max.max(max_y);
             }
         }
@@ -22,52 +25,50 @@ impl Solution for Day17 {
     }
 
     fn part2(&self, input: &str) -> String {
-        let (x_range, y_range) = parse_input(input);
+        let (x_range, y_range) = parse_input(input).expect("Invalid input");
 
-        let mut all_hit: HashSet<(isize, isize)> = HashSet::new();
+        let mut all_hit: HashSet<Point> = HashSet::new();
         for v_x in potential_v_x(&x_range) {
             for v_y in -1000..1000 {
-                let hit = max_y_if_hit(v_x, v_y, &x_range, &y_range);
-                hit.map(|_it| all_hit.insert((v_x, v_y)));
+                let velocity = Point::new(v_x, v_y);
+                let hit = max_y_if_hit(velocity, &x_range, &y_range);
+                hit.map(|_it| all_hit.insert(velocity));
             }
         }
         all_hit.len().to_string()
     }
 }
 
-fn potential_v_x(x_range: &RangeInclusive<isize>) -> RangeInclusive<isize> {
-    0..=*x_range.end()
+fn potential_v_x(x_range: &Interval<isize>) -> RangeInclusive<isize> {
+    0..=x_range.end()
 }
 
 fn max_y_if_hit(
-    start_v_x: isize,
-    start_v_y: isize,
-    x_range: &RangeInclusive<isize>,
-    y_range: &RangeInclusive<isize>,
+    start_velocity: Point,
+    x_range: &Interval<isize>,
+    y_range: &Interval<isize>,
 ) -> Option<isize> {
-    let (mut x_pos, mut y_pos) = (0, 0);
-    let (mut v_x, mut v_y) = (start_v_x, start_v_y);
+    let mut pos = Point::new(0, 0);
+    let mut velocity = start_velocity;
 
-    let x_bound_max = *x_range.end();
-    let y_bound_min = *y_range.start();
+    let x_bound_max = x_range.end();
+    let y_bound_min = y_range.start();
 
-    let mut max_y = y_pos;
+    let mut max_y = pos.y;
     let mut hit_target_area = false;
     loop {
-        x_pos += v_x;
-        y_pos += v_y;
-        max_y = max(max_y, y_pos);
+        pos += velocity;
+        max_y = max(max_y, pos.y);
 
-        if x_range.contains(&x_pos) && y_range.contains(&y_pos) {
+        if x_range.contains(pos.x) && y_range.contains(pos.y) {
             hit_target_area = true;
         }
 
-        if x_pos > x_bound_max || y_pos < y_bound_min {
+        if pos.x > x_bound_max || pos.y < y_bound_min {
             break;
         }
 
-        v_x = next_x_velocity(v_x);
-        v_y = next_y_velocity(v_y);
+        velocity = next_velocity(velocity);
     }
 
     if hit_target_area {
@@ -77,31 +78,31 @@ fn max_y_if_hit(
     }
 }
 
-fn next_x_velocity(v_prev: isize) -> isize {
-    v_prev
-        + match v_prev.cmp(&0) {
-            Ordering::Less => 1,
-            Ordering::Equal => 0,
-            Ordering::Greater => -1,
-        }
+/// Drag pulls the x velocity towards 0, gravity pulls the y velocity down.
+fn next_velocity(v_prev: Point) -> Point {
+    v_prev - Point::new(v_prev.x.signum(), 1)
 }
 
-fn next_y_velocity(v_prev: isize) -> isize {
-    v_prev - 1
+fn parse_input(input: &str) -> ParseResult<(Interval<isize>, Interval<isize>)> {
+    let span = Span::new(input).trim();
+    let ranges = span
+        .strip_prefix("target area: ")
+        .ok_or_else(|| span.error("expected `target area: `"))?;
+    let (x_range, y_range) = ranges
+        .split_once(", ")
+        .ok_or_else(|| ranges.error("expected `x=<range>, y=<range>`"))?;
+    Ok((parse_range(x_range, "x=")?, parse_range(y_range, "y=")?))
 }
 
-fn parse_input(input: &str) -> (RangeInclusive<isize>, RangeInclusive<isize>) {
-    let regex = Regex::new(r"-?\d+").unwrap();
-    match regex
-        .captures_iter(input)
-        .map(|m| m.get(0).unwrap().as_str())
-        .map(|it| it.parse::<isize>().unwrap())
-        .collect_vec()
-        .as_slice()
-    {
-        [x1, x2, y1, y2] => (*x1..=*x2, *y1..=*y2),
-        _ => panic!("Invalid input: {}", input),
-    }
+fn parse_range(span: Span, prefix: &str) -> ParseResult<Interval<isize>> {
+    let bounds = span
+        .strip_prefix(prefix)
+        .ok_or_else(|| span.error(format!("expected `{}`", prefix)))?;
+    let (start, end) = bounds
+        .split_once("..")
+        .ok_or_else(|| bounds.error("expected `<start>..<end>`"))?;
+    Interval::new(start.parse()?, end.parse()?)
+        .ok_or_else(|| bounds.error("expected the start of the range to be at most its end"))
 }
 
 #[cfg(test)]
@@ -109,7 +110,7 @@ mod tests {
     use crate::days::day17::Day17;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "target area: x=20..30, y=-10..-5";
+    const SAMPLE_1: &str = "target area: x=20..30, y=-10..-5";
 
     #[test]
     fn test_sample_pt1() {
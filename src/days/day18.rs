@@ -1,12 +1,12 @@
+use crate::days::parse::{ParseResult, Span};
 use crate::Solution;
-use itertools::FoldWhile::{Continue, Done};
 use itertools::Itertools;
 
 pub struct Day18 {}
 
 impl Solution for Day18 {
     fn part1(&self, input: &str) -> String {
-        let pairs = input.trim().lines().map(parse_pair).collect_vec();
+        let pairs = parse_input(input).expect("Invalid input");
 
         let reduced: Node = pairs
             .iter()
@@ -17,7 +17,7 @@ impl Solution for Day18 {
     }
 
     fn part2(&self, input: &str) -> String {
-        let pairs = input.trim().lines().map(parse_pair).collect_vec();
+        let pairs = parse_input(input).expect("Invalid input");
 
         pairs
             .iter()
@@ -27,6 +27,12 @@ impl Solution for Day18 {
             .unwrap()
             .to_string()
     }
+
+    #[cfg(feature = "serde")]
+    fn to_json(&self, input: &str) -> Option<serde_json::Value> {
+        let numbers = parse_input(input).expect("Invalid input");
+        Some(serde_json::json!(numbers))
+    }
 }
 
 fn pairwise_add(n1: &Node, n2: &Node) -> Node {
@@ -38,7 +44,7 @@ fn pairwise_add(n1: &Node, n2: &Node) -> Node {
     while reduced
         .explode(0)
         .map(|_| ())
-        .or_else(|| reduced.split(0))
+        .or_else(|| reduced.split())
         .is_some()
     {
         // keep reducing
@@ -47,40 +53,60 @@ fn pairwise_add(n1: &Node, n2: &Node) -> Node {
     reduced
 }
 
-fn parse_pair(input: &str) -> Node {
-    if let Ok(n) = input.parse::<usize>() {
-        // input part is a 'regular number'
-        Node::Literal { value: n }
-    } else {
-        // find the top-level pair and recurse
-        let (_, split_i) = input
+/// One snailfish number per line.
+fn parse_input(input: &str) -> ParseResult<Vec<Node>> {
+    Span::new(input)
+        .trim()
+        .lines()
+        .map(|line| {
+            let (node, rest) = parse_node(line.trim())?;
+            if !rest.is_empty() {
+                return Err(rest.error(format!("unexpected `{}` after the number", rest.text)));
+            }
+            Ok(node)
+        })
+        .collect()
+}
+
+/// Reads a regular number or a `[left,right]` pair from the start of `span`, returning it along
+/// with whatever follows it.
+fn parse_node(span: Span) -> ParseResult<(Node, Span)> {
+    if let Some(rest) = span.strip_prefix("[") {
+        let (left, rest) = parse_node(rest)?;
+        let (right, rest) = parse_node(expect_token(rest, ",")?)?;
+        let pair = Node::Pair {
+            left: Box::new(left),
+            right: Box::new(right),
+        };
+        return Ok((pair, expect_token(rest, "]")?));
+    }
+
+    let digits = span.text.len()
+        - span
+            .text
+            .trim_start_matches(|ch: char| ch.is_ascii_digit())
+            .len();
+    if digits == 0 {
+        let found = span
+            .text
             .chars()
-            .enumerate()
-            .fold_while((0, 0), |(depth, split), (i, ch)| match ch {
-                '[' => Continue((depth + 1, split)),
-                ']' => Continue((depth - 1, split)),
-                ',' => {
-                    if depth == 1 {
-                        Done((1, i))
-                    } else {
-                        Continue((depth, split))
-                    }
-                }
-                _ => Continue((depth, split)),
-            })
-            .into_inner();
-
-        Node::Pair {
-            // skip leading '['
-            left: Box::new(parse_pair(&input[1..split_i])),
-            // skip trailing ']'
-            right: Box::new(parse_pair(&input[split_i + 1..input.len() - 1])),
-        }
+            .next()
+            .map_or("the end of the line".to_string(), |ch| format!("`{}`", ch));
+        return Err(span.error(format!("expected a number or `[`, found {}", found)));
     }
+    let value = span.slice(0, digits).parse()?;
+    Ok((Node::Literal { value }, span.advance(digits)))
+}
+
+fn expect_token<'a>(span: Span<'a>, token: &str) -> ParseResult<Span<'a>> {
+    span.strip_prefix(token)
+        .ok_or_else(|| span.error(format!("expected `{}`", token)))
 }
 
 #[derive(Clone, Debug, PartialEq)]
-enum Node {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(untagged))]
+pub enum Node {
     Literal { value: usize },
     Pair { left: Box<Node>, right: Box<Node> },
 }
@@ -96,7 +122,7 @@ impl Node {
     }
 
     fn explode(&mut self, depth: usize) -> Option<(LeftValue, RightValue)> {
-        return match self {
+        match self {
             Node::Literal { value: _ } => None,
             Node::Pair { left, right } => {
                 if depth == 4 {
@@ -120,7 +146,7 @@ impl Node {
                     None
                 }
             }
-        };
+        }
     }
 
     fn propagate_explode(&mut self, reduce_left: bool, add: usize) {
@@ -138,7 +164,7 @@ impl Node {
         }
     }
 
-    fn split(&mut self, depth: usize) -> Option<()> {
+    fn split(&mut self) -> Option<()> {
         match self {
             Node::Literal { value } => {
                 let v = *value;
@@ -152,20 +178,19 @@ impl Node {
                     None
                 }
             }
-            Node::Pair { left, right } => left
-                .split(depth + 1)
-                .or_else(|| right.split(depth + 1))
-                .or(None),
+            Node::Pair { left, right } => left.split().or_else(|| right.split()).or(None),
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::days::day18::Day18;
+    #[cfg(feature = "serde")]
+    use crate::days::day18::Node;
+    use crate::days::day18::{parse_input, Day18};
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+    const SAMPLE_1: &str = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
 [[[5,[2,8]],4],[5,[[9,9],0]]]
 [6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
 [[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
@@ -185,4 +210,31 @@ mod tests {
     fn test_sample_pt2() {
         assert_eq!(3993.to_string(), Day18 {}.part2(SAMPLE_1));
     }
+
+    #[test]
+    fn test_malformed_numbers() {
+        let error = parse_input("[[1,2],3]\n[[1,2];3]").unwrap_err();
+        assert_eq!((2, 7), (error.line, error.column));
+        let error = parse_input("[1,[2,x]]").unwrap_err();
+        assert_eq!((1, 7), (error.line, error.column));
+        let error = parse_input("[1,2]]").unwrap_err();
+        assert_eq!((1, 6), (error.line, error.column));
+        assert!(parse_input("[1,[2,3]").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_round_trip() {
+        let json = Day18 {}.to_json("[[1,2],3]").unwrap();
+        assert_eq!(
+            serde_json::json!([{
+                "left": { "left": { "value": 1 }, "right": { "value": 2 } },
+                "right": { "value": 3 }
+            }]),
+            json
+        );
+
+        let nodes: Vec<Node> = serde_json::from_value(json).unwrap();
+        assert_eq!(parse_input("[[1,2],3]").unwrap(), nodes);
+    }
 }
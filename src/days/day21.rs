@@ -1,3 +1,6 @@
+use crate::days::counter::Counter;
+use crate::days::parse::{ParseResult, Span};
+use crate::days::simulation::Simulation;
 use crate::Solution;
 use itertools::Itertools;
 use std::collections::HashMap;
@@ -9,19 +12,18 @@ const PT2_SCORE_TO_WIN: usize = 21;
 
 impl Solution for Day21 {
     fn part1(&self, input: &str) -> String {
-        let (mut player1, mut player2) = init_players(input);
-        let mut die = SeqDie100 {
-            cur_roll: 1,
-            n_rolls: 0,
+        let (player1, player2) = init_players(input);
+        let mut game = DeterministicGame {
+            to_play: player1,
+            just_played: player2,
+            die: SeqDie100 {
+                cur_roll: 1,
+                n_rolls: 0,
+            },
         };
 
-        loop {
-            player1.play_turn(&mut die);
-            if player1.score >= PT1_SCORE_TO_WIN {
-                return (player2.score * die.n_rolls).to_string();
-            }
-            std::mem::swap(&mut player1, &mut player2);
-        }
+        game.run_until(|game, _| game.just_played.score >= PT1_SCORE_TO_WIN);
+        (game.to_play.score * game.die.n_rolls).to_string()
     }
 
     fn part2(&self, input: &str) -> String {
@@ -34,14 +36,19 @@ impl Solution for Day21 {
 }
 
 fn init_players(input: &str) -> (Player, Player) {
-    let lines = input.trim().lines().collect_vec();
-    let parse_player_start: fn(&str) -> usize = |it| {
-        it.split_once(": ")
-            .map(|it| it.1.parse::<usize>().unwrap())
-            .unwrap()
+    let lines = Span::new(input).trim().lines().collect_vec();
+    let parse_player_start: fn(&Span) -> ParseResult<usize> = |line| {
+        line.split_once(": ")
+            .ok_or_else(|| line.error("expected `Player <n> starting position: <pos>`"))?
+            .1
+            .trim()
+            .parse::<usize>()
     };
     let (p1_start, p2_start) = match lines.as_slice() {
-        [p1_str, p2_str] => (parse_player_start(p1_str), parse_player_start(p2_str)),
+        [p1_str, p2_str] => (
+            parse_player_start(p1_str).expect("Invalid input"),
+            parse_player_start(p2_str).expect("Invalid input"),
+        ),
         _ => panic!("Invalid input"),
     };
 
@@ -62,7 +69,7 @@ fn play_quantum(
     p1: Player,
     p2: Player,
 ) -> (usize, usize) {
-    return if p2.score >= PT2_SCORE_TO_WIN {
+    if p2.score >= PT2_SCORE_TO_WIN {
         (0, 1)
     } else if let Some(score) = memo.get(&(p1, p2)) {
         *score
@@ -79,7 +86,7 @@ fn play_quantum(
 
         memo.insert((p1, p2), (wins_p1, wins_p2));
         (wins_p1, wins_p2)
-    };
+    }
 }
 
 fn rolls_to_occurrences() -> Vec<(usize, usize)> {
@@ -92,11 +99,8 @@ fn rolls_to_occurrences() -> Vec<(usize, usize)> {
         }
     }
 
-    let mut map: HashMap<usize, usize> = HashMap::new();
-    for it in possible_total_rolls {
-        *map.entry(it).or_default() += 1
-    }
-    map.iter().map(|it| (*it.0, *it.1)).collect_vec()
+    let counter: Counter<usize> = possible_total_rolls.into_iter().collect();
+    counter.into_iter().collect_vec()
 }
 
 trait Die {
@@ -108,6 +112,22 @@ struct SeqDie100 {
     n_rolls: usize,
 }
 
+/// A game with the deterministic die, one player's turn per step.
+struct DeterministicGame {
+    to_play: Player,
+    just_played: Player,
+    die: SeqDie100,
+}
+
+impl Simulation for DeterministicGame {
+    type Output = ();
+
+    fn step(&mut self) {
+        self.to_play.play_turn(&mut self.die);
+        std::mem::swap(&mut self.to_play, &mut self.just_played);
+    }
+}
+
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Copy)]
 struct Player {
     pos: usize,
@@ -141,7 +161,7 @@ mod tests {
     use crate::days::day21::Day21;
     use crate::Solution;
 
-    const SAMPLE_1: &'static str = "Player 1 starting position: 4
+    const SAMPLE_1: &str = "Player 1 starting position: 4
 Player 2 starting position: 8";
 
     #[test]
@@ -0,0 +1,336 @@
+//! 2D and 3D points over any integer coordinate type, with the neighbourhoods, unit steps and
+//! rotations the grid puzzles need.
+
+use std::fmt;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
+
+/// A coordinate type usable in [`Point`] and [`Point3`].
+pub trait Coord:
+    Copy + Ord + fmt::Debug + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self>
+{
+    const ZERO: Self;
+    const ONE: Self;
+
+    fn abs_diff(self, other: Self) -> Self;
+}
+
+/// A coordinate type that can point in any direction.
+pub trait SignedCoord: Coord + Neg<Output = Self> {
+    fn signum(self) -> Self;
+}
+
+macro_rules! impl_coord {
+    ($($t:ty),*) => {$(
+        impl Coord for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+
+            fn abs_diff(self, other: Self) -> Self {
+                if self > other {
+                    self - other
+                } else {
+                    other - self
+                }
+            }
+        }
+    )*};
+}
+
+macro_rules! impl_signed_coord {
+    ($($t:ty),*) => {$(
+        impl SignedCoord for $t {
+            fn signum(self) -> Self {
+                <$t>::signum(self)
+            }
+        }
+    )*};
+}
+
+impl_coord!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+impl_signed_coord!(i8, i16, i32, i64, i128, isize);
+
+/// A 2D point, or the vector between two points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point<T> {
+    pub x: T,
+    pub y: T,
+}
+
+impl<T: Coord> Point<T> {
+    pub fn new(x: T, y: T) -> Point<T> {
+        Point { x, y }
+    }
+
+    pub fn manhattan_distance(&self, other: &Point<T>) -> T {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y)
+    }
+
+    /// The same point with coordinates of another type, if they all fit.
+    pub fn try_convert<U: TryFrom<T>>(self) -> Option<Point<U>> {
+        Some(Point {
+            x: U::try_from(self.x).ok()?,
+            y: U::try_from(self.y).ok()?,
+        })
+    }
+}
+
+impl<T: SignedCoord> Point<T> {
+    pub fn up() -> Point<T> {
+        Point::new(T::ZERO, -T::ONE)
+    }
+
+    pub fn down() -> Point<T> {
+        Point::new(T::ZERO, T::ONE)
+    }
+
+    pub fn left() -> Point<T> {
+        Point::new(-T::ONE, T::ZERO)
+    }
+
+    pub fn right() -> Point<T> {
+        Point::new(T::ONE, T::ZERO)
+    }
+
+    /// Unit steps to the 4 orthogonally adjacent points.
+    pub fn orthogonal_directions() -> [Point<T>; 4] {
+        [Self::left(), Self::right(), Self::down(), Self::up()]
+    }
+
+    /// Unit steps to all 8 adjacent points, diagonals included.
+    pub fn all_directions() -> [Point<T>; 8] {
+        let (z, o) = (T::ZERO, T::ONE);
+        [
+            Point::new(-o, z),
+            Point::new(-o, -o),
+            Point::new(-o, o),
+            Point::new(o, z),
+            Point::new(o, -o),
+            Point::new(o, o),
+            Point::new(z, o),
+            Point::new(z, -o),
+        ]
+    }
+
+    /// Each coordinate replaced by its sign (-1, 0 or 1).
+    pub fn signum(&self) -> Point<T> {
+        Point::new(self.x.signum(), self.y.signum())
+    }
+
+    /// The step, one unit or zero along each axis, that moves from this point towards `other`.
+    pub fn direction_to(&self, other: &Point<T>) -> Point<T> {
+        (*other - *self).signum()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Point<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/// A 3D point, or the vector between two points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Point3<T> {
+    pub x: T,
+    pub y: T,
+    pub z: T,
+}
+
+impl<T: Coord> Point3<T> {
+    pub fn new(x: T, y: T, z: T) -> Point3<T> {
+        Point3 { x, y, z }
+    }
+
+    pub fn manhattan_distance(&self, other: &Point3<T>) -> T {
+        self.x.abs_diff(other.x) + self.y.abs_diff(other.y) + self.z.abs_diff(other.z)
+    }
+
+    fn coords(&self) -> [T; 3] {
+        [self.x, self.y, self.z]
+    }
+}
+
+impl<T: SignedCoord> Point3<T> {
+    pub fn signum(&self) -> Point3<T> {
+        Point3::new(self.x.signum(), self.y.signum(), self.z.signum())
+    }
+
+    pub fn direction_to(&self, other: &Point3<T>) -> Point3<T> {
+        (*other - *self).signum()
+    }
+
+    pub fn rotate(&self, rotation: &Rotation) -> Point3<T> {
+        let coords = self.coords();
+        let axis = |i: usize| {
+            let value = coords[rotation.axes[i]];
+            if rotation.negate[i] {
+                -value
+            } else {
+                value
+            }
+        };
+        Point3::new(axis(0), axis(1), axis(2))
+    }
+
+    /// This point under each of the 24 rotations in [`Rotation::all`], in the same order.
+    pub fn rotations(&self) -> Vec<Point3<T>> {
+        Rotation::all().iter().map(|r| self.rotate(r)).collect()
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Point3<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}", self.x, self.y, self.z)
+    }
+}
+
+/// One of the 24 rotations that keep a cube axis-aligned. Output axis `i` takes input axis
+/// `axes[i]`, negated if `negate[i]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rotation {
+    axes: [usize; 3],
+    negate: [bool; 3],
+}
+
+impl Rotation {
+    pub const IDENTITY: Rotation = Rotation {
+        axes: [0, 1, 2],
+        negate: [false, false, false],
+    };
+
+    /// All 24 rotations, identity first: every signed permutation of the axes whose determinant
+    /// is +1 (the other 24 are reflections).
+    pub fn all() -> Vec<Rotation> {
+        const PERMUTATIONS: [([usize; 3], bool); 6] = [
+            ([0, 1, 2], false),
+            ([1, 2, 0], false),
+            ([2, 0, 1], false),
+            ([0, 2, 1], true),
+            ([2, 1, 0], true),
+            ([1, 0, 2], true),
+        ];
+
+        let mut rotations = Vec::with_capacity(24);
+        for (axes, odd) in PERMUTATIONS {
+            for signs in 0..8 {
+                let negate = [signs & 1 != 0, signs & 2 != 0, signs & 4 != 0];
+                let negations = negate.iter().filter(|&&n| n).count();
+                if (negations % 2 == 1) == odd {
+                    rotations.push(Rotation { axes, negate });
+                }
+            }
+        }
+        rotations
+    }
+
+    /// The rotation that undoes this one.
+    pub fn inverse(&self) -> Rotation {
+        let mut inverse = Rotation::IDENTITY;
+        for i in 0..3 {
+            inverse.axes[self.axes[i]] = i;
+            inverse.negate[self.axes[i]] = self.negate[i];
+        }
+        inverse
+    }
+}
+
+macro_rules! impl_ops {
+    ($name:ident { $($field:ident),+ }) => {
+        impl<T: Coord> Add for $name<T> {
+            type Output = $name<T>;
+
+            fn add(self, other: $name<T>) -> $name<T> {
+                $name { $($field: self.$field + other.$field),+ }
+            }
+        }
+
+        impl<T: Coord> Sub for $name<T> {
+            type Output = $name<T>;
+
+            fn sub(self, other: $name<T>) -> $name<T> {
+                $name { $($field: self.$field - other.$field),+ }
+            }
+        }
+
+        impl<T: Coord> Mul<T> for $name<T> {
+            type Output = $name<T>;
+
+            fn mul(self, scale: T) -> $name<T> {
+                $name { $($field: self.$field * scale),+ }
+            }
+        }
+
+        impl<T: SignedCoord> Neg for $name<T> {
+            type Output = $name<T>;
+
+            fn neg(self) -> $name<T> {
+                $name { $($field: -self.$field),+ }
+            }
+        }
+
+        impl<T: Coord> AddAssign for $name<T> {
+            fn add_assign(&mut self, other: $name<T>) {
+                *self = *self + other;
+            }
+        }
+
+        impl<T: Coord> SubAssign for $name<T> {
+            fn sub_assign(&mut self, other: $name<T>) {
+                *self = *self - other;
+            }
+        }
+    };
+}
+
+impl_ops!(Point { x, y });
+impl_ops!(Point3 { x, y, z });
+
+#[cfg(test)]
+mod tests {
+    use crate::days::geometry::{Point, Point3, Rotation};
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_point_ops() {
+        let a = Point::new(3, -2);
+        let b = Point::new(-1, 5);
+        assert_eq!(Point::new(2, 3), a + b);
+        assert_eq!(Point::new(4, -7), a - b);
+        assert_eq!(Point::new(-6, 4), -a * 2);
+        assert_eq!(11, a.manhattan_distance(&b));
+        assert_eq!(Point::new(-1, 1), a.direction_to(&b));
+        assert_eq!(
+            Point::new(0, 1),
+            Point::new(4, 0).direction_to(&Point::new(4, 9))
+        );
+    }
+
+    #[test]
+    fn test_unsigned_points() {
+        let a: Point<usize> = Point::new(2, 9);
+        assert_eq!(8, a.manhattan_distance(&Point::new(5, 4)));
+        assert_eq!(Some(Point::new(2isize, 9)), a.try_convert());
+        assert_eq!(None, Point::new(-1isize, 0).try_convert::<usize>());
+    }
+
+    #[test]
+    fn test_24_distinct_rotations() {
+        let p = Point3::new(1, 2, 3);
+        let rotated: HashSet<Point3<i32>> = p.rotations().into_iter().collect();
+        assert_eq!(24, rotated.len());
+        assert_eq!(p, p.rotate(&Rotation::all()[0]));
+        // rotations keep the distance from the origin and never mirror
+        assert!(rotated.contains(&Point3::new(-2, 1, 3)));
+        assert!(!rotated.contains(&Point3::new(-1, 2, 3)));
+    }
+
+    #[test]
+    fn test_rotation_inverse() {
+        let p = Point3::new(5, -7, 11);
+        for rotation in Rotation::all() {
+            assert_eq!(p, p.rotate(&rotation).rotate(&rotation.inverse()));
+        }
+    }
+}
@@ -1,17 +1,31 @@
+//! A directed or undirected graph stored as vectors of nodes and edges, with DOT export.
+
 // Graph code inspired/modified from this example implementation by Nicholas D. Matsakis:
 //   http://smallcultfollowing.com/babysteps/blog/2015/04/06/modeling-graphs-in-rust-using-vector-indices/
 
+use crate::days::parse::{ParseResult, Span};
 use std::collections::HashMap;
 use std::fmt::{Error, Formatter};
 
+/// A graph of string-identified nodes whose edges carry data of type `E`. Each node keeps a singly
+/// linked list of the edges it can leave by: a directed edge is linked from its source only, an
+/// undirected edge from both of its endpoints.
 #[derive(Debug)]
-pub struct Graph {
+pub struct Graph<E = ()> {
     pub nodes: Vec<NodeData>,
-    pub edges: Vec<EdgeData>,
+    edges: Vec<Option<EdgeData<E>>>,
     pub node_index_by_id: HashMap<String, NodeIndex>,
 }
 
-impl Graph {
+impl<E> Graph<E> {
+    pub fn new() -> Graph<E> {
+        Graph {
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            node_index_by_id: HashMap::new(),
+        }
+    }
+
     pub fn node_index(&self, id: &str) -> Option<NodeIndex> {
         self.node_index_by_id.get(id).copied()
     }
@@ -24,6 +38,7 @@ impl Graph {
                 self.nodes.push(NodeData {
                     id: id.to_string(),
                     first_outgoing_edge: None,
+                    in_degree: 0,
                 });
                 self.node_index_by_id.insert(id.to_string(), index);
                 index
@@ -31,25 +46,147 @@ impl Graph {
         }
     }
 
-    pub fn add_edge(&mut self, source: NodeIndex, target: NodeIndex) {
+    pub fn add_edge_with_data(
+        &mut self,
+        source: NodeIndex,
+        target: NodeIndex,
+        data: E,
+    ) -> EdgeIndex {
+        self.insert_edge(source, target, true, data)
+    }
+
+    pub fn add_undirected_edge_with_data(
+        &mut self,
+        a: NodeIndex,
+        b: NodeIndex,
+        data: E,
+    ) -> EdgeIndex {
+        self.insert_edge(a, b, false, data)
+    }
+
+    fn insert_edge(
+        &mut self,
+        source: NodeIndex,
+        target: NodeIndex,
+        directed: bool,
+        data: E,
+    ) -> EdgeIndex {
         let edge_index = self.edges.len();
-        let node_data = &mut self.nodes[source];
-        self.edges.push(EdgeData {
+        let mut next_outgoing_edge = [self.nodes[source].first_outgoing_edge, None];
+        self.nodes[source].first_outgoing_edge = Some(edge_index);
+        self.nodes[target].in_degree += 1;
+
+        // an undirected self-loop is only linked once, so it is only traversed once
+        if !directed && source != target {
+            next_outgoing_edge[1] = self.nodes[target].first_outgoing_edge;
+            self.nodes[target].first_outgoing_edge = Some(edge_index);
+            self.nodes[source].in_degree += 1;
+        }
+
+        self.edges.push(Some(EdgeData {
+            source,
             target,
-            next_outgoing_edge: node_data.first_outgoing_edge,
-        });
-        node_data.first_outgoing_edge = Some(edge_index);
+            directed,
+            data,
+            next_outgoing_edge,
+        }));
+        edge_index
     }
 
-    pub fn successors(&self, source: NodeIndex) -> Successors {
-        let first_outgoing_edge = self.nodes[source].first_outgoing_edge;
-        Successors {
+    /// Removes the edge that can be followed from `source` to `target`, returning its data.
+    pub fn remove_edge(&mut self, source: NodeIndex, target: NodeIndex) -> Option<E> {
+        let edge_index = self.find_edge(source, target)?;
+        let (edge_source, edge_target, directed) = {
+            let edge = self.edge(edge_index).unwrap();
+            (edge.source, edge.target, edge.directed)
+        };
+
+        self.unlink_edge(edge_source, edge_index);
+        self.nodes[edge_target].in_degree -= 1;
+        if !directed && edge_source != edge_target {
+            self.unlink_edge(edge_target, edge_index);
+            self.nodes[edge_source].in_degree -= 1;
+        }
+
+        self.edges[edge_index].take().map(|edge| edge.data)
+    }
+
+    fn unlink_edge(&mut self, node: NodeIndex, edge_index: EdgeIndex) {
+        let next = self.edge(edge_index).unwrap().next_for(node);
+
+        let mut prev: Option<EdgeIndex> = None;
+        let mut cur = self.nodes[node].first_outgoing_edge;
+        while let Some(cur_index) = cur {
+            if cur_index == edge_index {
+                break;
+            }
+            prev = Some(cur_index);
+            cur = self.edge(cur_index).unwrap().next_for(node);
+        }
+
+        match prev {
+            None => self.nodes[node].first_outgoing_edge = next,
+            Some(prev_index) => {
+                let prev_edge = self.edges[prev_index].as_mut().unwrap();
+                let slot = prev_edge.slot_for(node);
+                prev_edge.next_outgoing_edge[slot] = next;
+            }
+        }
+    }
+
+    /// The edge that can be followed from `source` to `target`, if there is one.
+    pub fn find_edge(&self, source: NodeIndex, target: NodeIndex) -> Option<EdgeIndex> {
+        self.outgoing_edges(source)
+            .find(|&edge_index| self.edge(edge_index).unwrap().other_end(source) == target)
+    }
+
+    pub fn has_edge(&self, source: NodeIndex, target: NodeIndex) -> bool {
+        self.find_edge(source, target).is_some()
+    }
+
+    pub fn edge(&self, edge_index: EdgeIndex) -> Option<&EdgeData<E>> {
+        self.edges.get(edge_index).and_then(|edge| edge.as_ref())
+    }
+
+    pub fn edge_data_mut(&mut self, edge_index: EdgeIndex) -> Option<&mut E> {
+        self.edges
+            .get_mut(edge_index)
+            .and_then(|edge| edge.as_mut())
+            .map(|edge| &mut edge.data)
+    }
+
+    /// All edges that haven't been removed, in insertion order.
+    pub fn edges(&self) -> impl Iterator<Item = (EdgeIndex, &EdgeData<E>)> {
+        self.edges
+            .iter()
+            .enumerate()
+            .filter_map(|(index, edge)| edge.as_ref().map(|it| (index, it)))
+    }
+
+    /// Number of edges that can be followed out of `node`.
+    pub fn out_degree(&self, node: NodeIndex) -> usize {
+        self.outgoing_edges(node).count()
+    }
+
+    /// Number of edges that can be followed into `node`.
+    pub fn in_degree(&self, node: NodeIndex) -> usize {
+        self.nodes[node].in_degree
+    }
+
+    fn outgoing_edges(&self, source: NodeIndex) -> OutgoingEdges<'_, E> {
+        OutgoingEdges {
             graph: self,
-            current_edge_index: first_outgoing_edge,
+            node: source,
+            current_edge_index: self.nodes[source].first_outgoing_edge,
+        }
+    }
+
+    pub fn successors(&self, source: NodeIndex) -> Successors<'_, E> {
+        Successors {
+            edges: self.outgoing_edges(source),
         }
     }
 
-    #[allow(dead_code)]
     pub fn all_successors(&self, source: NodeIndex) -> Vec<NodeIndex> {
         let mut successors = Vec::new();
         self.all_successors_impl(source, &mut successors);
@@ -64,25 +201,140 @@ impl Graph {
             }
         }
     }
+
+    /// Renders the graph as a GraphViz DOT digraph, asking `style` for each node's label and for
+    /// any extra node/edge attributes. Undirected edges are drawn once, without an arrowhead.
+    pub fn to_dot(&self, name: &str, style: &dyn DotStyle) -> String {
+        let mut dot = format!("digraph {} {{\n", quote_dot_id(name));
+
+        for node in &self.nodes {
+            let mut attributes = vec![("label", style.node_label(node))];
+            attributes.extend(style.node_attributes(node));
+            dot.push_str(&format!(
+                "    {} [{}];\n",
+                quote_dot_id(&node.id),
+                format_dot_attributes(&attributes)
+            ));
+        }
+
+        for (_, edge) in self.edges() {
+            let source_node = &self.nodes[edge.source];
+            let target_node = &self.nodes[edge.target];
+
+            let mut attributes = Vec::new();
+            if !edge.directed {
+                attributes.push(("dir", "none".to_string()));
+            }
+            attributes.extend(style.edge_attributes(source_node, target_node));
+
+            dot.push_str(&format!(
+                "    {} -> {}",
+                quote_dot_id(&source_node.id),
+                quote_dot_id(&target_node.id)
+            ));
+            if !attributes.is_empty() {
+                dot.push_str(&format!(" [{}]", format_dot_attributes(&attributes)));
+            }
+            dot.push_str(";\n");
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+impl<E: Default> Graph<E> {
+    pub fn add_edge(&mut self, source: NodeIndex, target: NodeIndex) -> EdgeIndex {
+        self.add_edge_with_data(source, target, E::default())
+    }
+
+    pub fn add_undirected_edge(&mut self, a: NodeIndex, b: NodeIndex) -> EdgeIndex {
+        self.add_undirected_edge_with_data(a, b, E::default())
+    }
+
+    /// Builds a graph of undirected edges from lines of the form `a-b`.
+    pub fn from_edge_list(input: &str) -> ParseResult<Graph<E>> {
+        let mut graph = Graph::new();
+        for line in Span::new(input).trim().lines() {
+            let line = line.trim();
+            let (a, b) = match line.split_once("-") {
+                Some((a, b)) if !a.is_empty() && !b.is_empty() => (a, b),
+                _ => return Err(line.error(format!("expected `a-b`, found `{}`", line.text))),
+            };
+            let a_n = graph.add_node(a.text);
+            let b_n = graph.add_node(b.text);
+            graph.add_undirected_edge(a_n, b_n);
+        }
+        Ok(graph)
+    }
+}
+
+impl<E> Default for Graph<E> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Styling hooks for [`Graph::to_dot`]. Attributes are GraphViz `(name, value)` pairs; values are
+/// quoted on output.
+pub trait DotStyle {
+    fn node_label(&self, node: &NodeData) -> String {
+        node.id.to_string()
+    }
+
+    fn node_attributes(&self, _node: &NodeData) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+
+    fn edge_attributes(
+        &self,
+        _source: &NodeData,
+        _target: &NodeData,
+    ) -> Vec<(&'static str, String)> {
+        Vec::new()
+    }
+}
+
+fn quote_dot_id(id: &str) -> String {
+    format!("\"{}\"", id.replace('\\', "\\\\").replace('"', "\\\""))
 }
 
-pub struct Successors<'graph> {
-    graph: &'graph Graph,
+fn format_dot_attributes(attributes: &[(&str, String)]) -> String {
+    attributes
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, quote_dot_id(value)))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+struct OutgoingEdges<'graph, E> {
+    graph: &'graph Graph<E>,
+    node: NodeIndex,
     current_edge_index: Option<EdgeIndex>,
 }
 
-impl<'graph> Iterator for Successors<'graph> {
+impl<'graph, E> Iterator for OutgoingEdges<'graph, E> {
+    type Item = EdgeIndex;
+
+    fn next(&mut self) -> Option<EdgeIndex> {
+        let edge_num = self.current_edge_index?;
+        self.current_edge_index = self.graph.edge(edge_num).unwrap().next_for(self.node);
+        Some(edge_num)
+    }
+}
+
+pub struct Successors<'graph, E> {
+    edges: OutgoingEdges<'graph, E>,
+}
+
+impl<'graph, E> Iterator for Successors<'graph, E> {
     type Item = NodeIndex;
 
     fn next(&mut self) -> Option<NodeIndex> {
-        match self.current_edge_index {
-            None => None,
-            Some(edge_num) => {
-                let edge = &self.graph.edges[edge_num];
-                self.current_edge_index = edge.next_outgoing_edge;
-                Some(edge.target)
-            }
-        }
+        let node = self.edges.node;
+        self.edges
+            .next()
+            .map(|edge_num| self.edges.graph.edge(edge_num).unwrap().other_end(node))
     }
 }
 
@@ -93,6 +345,8 @@ pub type EdgeIndex = usize;
 pub struct NodeData {
     pub id: String,
     first_outgoing_edge: Option<EdgeIndex>,
+    /// How many edges can be followed into this node, kept up to date as edges come and go.
+    in_degree: usize,
 }
 
 impl std::fmt::Display for NodeData {
@@ -102,7 +356,121 @@ impl std::fmt::Display for NodeData {
 }
 
 #[derive(Debug)]
-pub struct EdgeData {
-    target: NodeIndex,
-    next_outgoing_edge: Option<EdgeIndex>,
+pub struct EdgeData<E = ()> {
+    pub source: NodeIndex,
+    pub target: NodeIndex,
+    pub directed: bool,
+    pub data: E,
+    // next edge in the source's list, then (for undirected edges) in the target's list
+    next_outgoing_edge: [Option<EdgeIndex>; 2],
+}
+
+impl<E> EdgeData<E> {
+    fn slot_for(&self, node: NodeIndex) -> usize {
+        if node == self.source {
+            0
+        } else {
+            1
+        }
+    }
+
+    fn next_for(&self, node: NodeIndex) -> Option<EdgeIndex> {
+        self.next_outgoing_edge[self.slot_for(node)]
+    }
+
+    fn other_end(&self, node: NodeIndex) -> NodeIndex {
+        if node == self.source {
+            self.target
+        } else {
+            self.source
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::graph::Graph;
+
+    const EDGE_LIST: &str = "a-b
+b-c
+c-a
+c-d";
+
+    #[test]
+    fn test_from_edge_list() {
+        let graph: Graph = Graph::from_edge_list(EDGE_LIST).unwrap();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|id| graph.node_index(id).unwrap());
+
+        assert_eq!(4, graph.nodes.len());
+        assert_eq!(4, graph.edges().count());
+        assert!(graph.has_edge(a, b) && graph.has_edge(b, a));
+        assert!(graph.has_edge(d, c));
+        assert!(!graph.has_edge(a, d));
+        assert_eq!(3, graph.out_degree(c));
+        assert_eq!(3, graph.in_degree(c));
+        assert_eq!(1, graph.in_degree(d));
+    }
+
+    #[test]
+    fn test_from_edge_list_error() {
+        let err = Graph::<()>::from_edge_list("a-b\nb c\n").unwrap_err();
+        assert_eq!((2, 1), (err.line, err.column));
+    }
+
+    #[test]
+    fn test_directed_and_undirected_degrees() {
+        let mut graph: Graph = Graph::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        graph.add_edge(a, b);
+        graph.add_undirected_edge(b, c);
+        graph.add_undirected_edge(c, c);
+
+        assert!(graph.has_edge(a, b));
+        assert!(!graph.has_edge(b, a));
+        assert_eq!((1, 0), (graph.out_degree(a), graph.in_degree(a)));
+        assert_eq!((1, 2), (graph.out_degree(b), graph.in_degree(b)));
+        assert_eq!((2, 2), (graph.out_degree(c), graph.in_degree(c)));
+    }
+
+    #[test]
+    fn test_remove_edge() {
+        let mut graph: Graph = Graph::from_edge_list(EDGE_LIST).unwrap();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|id| graph.node_index(id).unwrap());
+
+        // removing an undirected edge from either end removes it for both
+        assert_eq!(Some(()), graph.remove_edge(b, c));
+        assert!(!graph.has_edge(b, c) && !graph.has_edge(c, b));
+        assert_eq!(None, graph.remove_edge(c, b));
+
+        // the rest of each node's edge list is untouched
+        assert!(graph.has_edge(a, b) && graph.has_edge(c, a) && graph.has_edge(c, d));
+        assert_eq!(vec![a], graph.successors(b).collect::<Vec<_>>());
+        assert_eq!(3, graph.edges().count());
+        assert_eq!((1, 2), (graph.in_degree(b), graph.in_degree(c)));
+
+        let mut directed: Graph = Graph::new();
+        let [a, b] = ["a", "b"].map(|id| directed.add_node(id));
+        directed.add_edge(a, b);
+        directed.add_undirected_edge(b, b);
+        assert_eq!(Some(()), directed.remove_edge(a, b));
+        assert_eq!((0, 1), (directed.in_degree(a), directed.in_degree(b)));
+        assert_eq!(Some(()), directed.remove_edge(b, b));
+        assert_eq!(0, directed.in_degree(b));
+    }
+
+    #[test]
+    fn test_edge_data() {
+        let mut graph: Graph<u32> = Graph::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let edge = graph.add_undirected_edge_with_data(a, b, 7);
+
+        assert_eq!(Some(edge), graph.find_edge(b, a));
+        *graph.edge_data_mut(edge).unwrap() += 1;
+        assert_eq!(8, graph.edge(edge).unwrap().data);
+        assert_eq!(Some(8), graph.remove_edge(a, b));
+        assert!(graph.edge(edge).is_none());
+    }
 }
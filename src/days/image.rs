@@ -0,0 +1,273 @@
+//! Images of grids and point sets, saved as PNG or PPM.
+
+use crate::days::counter::Counter;
+use crate::days::geometry::{Coord, Point};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    pub const BLACK: Rgb = Rgb(0, 0, 0);
+    pub const WHITE: Rgb = Rgb(255, 255, 255);
+}
+
+/// A hex color like `ffc800` or `#ffc800`.
+impl FromStr for Rgb {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Rgb, String> {
+        let hex = s.strip_prefix('#').unwrap_or(s);
+        if hex.len() != 6 || !hex.chars().all(|ch| ch.is_ascii_hexdigit()) {
+            return Err(format!("expected a color like `ffc800`, found `{}`", s));
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        Ok(Rgb(channel(0), channel(2), channel(4)))
+    }
+}
+
+/// How cell values are turned into colors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Palette {
+    /// Values from 0 up to the largest value in the image shade evenly from the first color to
+    /// the second.
+    Gradient(Rgb, Rgb),
+    /// Value `i` gets the `i`-th color, and anything past the end gets the last color. With no
+    /// colors at all everything is black.
+    Discrete(Vec<Rgb>),
+}
+
+/// `gradient:<from>,<to>` or `discrete:<color>,<color>,...`, with colors in hex, e.g.
+/// `gradient:000000,ffc800`.
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Palette, String> {
+        let (kind, colors) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected `gradient:` or `discrete:` colors, found `{}`", s))?;
+        let colors = colors
+            .split(',')
+            .map(|color| color.trim().parse())
+            .collect::<Result<Vec<Rgb>, String>>()?;
+        match (kind, colors.as_slice()) {
+            ("gradient", &[from, to]) => Ok(Palette::Gradient(from, to)),
+            ("gradient", _) => Err(String::from("a gradient needs exactly two colors")),
+            ("discrete", _) => Ok(Palette::Discrete(colors)),
+            _ => Err(format!("unknown palette `{}`", kind)),
+        }
+    }
+}
+
+impl Palette {
+    pub fn color(&self, value: u64, max: u64) -> Rgb {
+        match self {
+            Palette::Gradient(from, to) => {
+                if max == 0 {
+                    return *from;
+                }
+                let channel = |from: u8, to: u8| {
+                    let (from, to) = (from as i128, to as i128);
+                    (from + (to - from) * value.min(max) as i128 / max as i128) as u8
+                };
+                Rgb(
+                    channel(from.0, to.0),
+                    channel(from.1, to.1),
+                    channel(from.2, to.2),
+                )
+            }
+            Palette::Discrete(colors) => {
+                let index = usize::try_from(value).unwrap_or(usize::MAX);
+                colors
+                    .get(index)
+                    .or_else(|| colors.last())
+                    .copied()
+                    .unwrap_or(Rgb::BLACK)
+            }
+        }
+    }
+}
+
+/// An RGB image, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    /// One pixel per value of a `width` by `height` grid given row by row.
+    pub fn from_grid(
+        width: usize,
+        height: usize,
+        values: impl IntoIterator<Item = u64>,
+        palette: &Palette,
+    ) -> Image {
+        let values: Vec<u64> = values.into_iter().collect();
+        assert_eq!(
+            width * height,
+            values.len(),
+            "Grid size doesn't match its values"
+        );
+
+        let max = values.iter().copied().max().unwrap_or(0);
+        Image {
+            width,
+            height,
+            pixels: values.iter().map(|&v| palette.color(v, max)).collect(),
+        }
+    }
+
+    /// One pixel per point from the origin to the furthest point, colored by how many times each
+    /// point appears (so unmarked pixels get the palette's color for 0).
+    pub fn from_points<T: Coord>(
+        points: impl IntoIterator<Item = Point<T>>,
+        palette: &Palette,
+    ) -> Image
+    where
+        usize: TryFrom<T>,
+    {
+        let density: Counter<Point<usize>, u64> = points
+            .into_iter()
+            .map(|p| {
+                p.try_convert()
+                    .expect("Points must have non-negative coordinates")
+            })
+            .collect();
+
+        let width = density.keys().map(|p| p.x + 1).max().unwrap_or(0);
+        let height = density.keys().map(|p| p.y + 1).max().unwrap_or(0);
+        let values = (0..height)
+            .flat_map(|y| (0..width).map(move |x| Point::new(x, y)))
+            .map(|p| density.get(&p));
+        Image::from_grid(width, height, values, palette)
+    }
+
+    /// The same image with every pixel blown up to a `scale` by `scale` square.
+    pub fn scaled(&self, scale: usize) -> Image {
+        let pixels = (0..self.height * scale)
+            .flat_map(|y| (0..self.width * scale).map(move |x| (x / scale, y / scale)))
+            .map(|(x, y)| self.pixels[y * self.width + x])
+            .collect();
+        Image {
+            width: self.width * scale,
+            height: self.height * scale,
+            pixels,
+        }
+    }
+
+    fn rgb_bytes(&self) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&Rgb(r, g, b)| [r, g, b])
+            .collect()
+    }
+
+    /// Writes a binary (`P6`) PPM.
+    pub fn write_ppm(&self, mut out: impl Write) -> io::Result<()> {
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        out.write_all(&self.rgb_bytes())
+    }
+
+    pub fn write_png(&self, out: impl Write) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.rgb_bytes())?;
+        writer.finish()
+    }
+
+    /// Writes the image to `path` as a PPM or PNG, depending on its extension.
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let extension = path.extension().and_then(|ext| ext.to_str());
+        match extension.map(|ext| ext.to_ascii_lowercase()).as_deref() {
+            Some("ppm") => self.write_ppm(BufWriter::new(File::create(path)?)),
+            Some("png") => self
+                .write_png(BufWriter::new(File::create(path)?))
+                .map_err(io::Error::other),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unsupported image format: {}", path.display()),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::geometry::Point;
+    use crate::days::image::{Image, Palette, Rgb};
+
+    #[test]
+    fn test_palettes() {
+        let gradient = Palette::Gradient(Rgb(0, 100, 200), Rgb(200, 100, 0));
+        assert_eq!(Rgb(0, 100, 200), gradient.color(0, 4));
+        assert_eq!(Rgb(100, 100, 100), gradient.color(2, 4));
+        assert_eq!(Rgb(200, 100, 0), gradient.color(4, 4));
+        assert_eq!(Rgb(0, 100, 200), gradient.color(0, 0));
+
+        let discrete = Palette::Discrete(vec![Rgb::WHITE, Rgb::BLACK]);
+        assert_eq!(Rgb::WHITE, discrete.color(0, 7));
+        assert_eq!(Rgb::BLACK, discrete.color(7, 7));
+        assert_eq!(Rgb::BLACK, Palette::Discrete(vec![]).color(3, 7));
+    }
+
+    #[test]
+    fn test_parse_palettes() {
+        assert_eq!(
+            Ok(Palette::Gradient(Rgb::BLACK, Rgb(255, 200, 0))),
+            "gradient:000000,#FFC800".parse()
+        );
+        assert_eq!(
+            Ok(Palette::Discrete(vec![Rgb::WHITE, Rgb(0, 0, 255)])),
+            "discrete:ffffff, 0000ff".parse()
+        );
+        assert!("gradient:000000".parse::<Palette>().is_err());
+        assert!("discrete:".parse::<Palette>().is_err());
+        assert!("discrete:fffff".parse::<Palette>().is_err());
+        assert!("rainbow:ffffff".parse::<Palette>().is_err());
+        assert!("ffffff".parse::<Palette>().is_err());
+    }
+
+    #[test]
+    fn test_from_points_counts_overlaps() {
+        let points = [Point::new(0, 1), Point::new(2, 1), Point::new(2, 1)];
+        let palette = Palette::Discrete(vec![Rgb(0, 0, 0), Rgb(1, 1, 1), Rgb(2, 2, 2)]);
+        let image = Image::from_points(points, &palette);
+
+        assert_eq!((3, 2), (image.width, image.height));
+        let shades = image.pixels.iter().map(|p| p.0).collect::<Vec<_>>();
+        assert_eq!(vec![0, 0, 0, 1, 0, 2], shades);
+    }
+
+    #[test]
+    fn test_scaled() {
+        let image = Image::from_grid(2, 1, [0, 1], &Palette::Gradient(Rgb::BLACK, Rgb::WHITE));
+        let scaled = image.scaled(2);
+        assert_eq!((4, 2), (scaled.width, scaled.height));
+        assert_eq!(Rgb::WHITE, scaled.pixels[7]);
+        assert_eq!(Rgb::BLACK, scaled.pixels[4]);
+    }
+
+    #[test]
+    fn test_encodings() {
+        let image = Image::from_grid(2, 1, [0, 1], &Palette::Gradient(Rgb::BLACK, Rgb::WHITE));
+
+        let mut ppm = Vec::new();
+        image.write_ppm(&mut ppm).unwrap();
+        assert_eq!(b"P6\n2 1\n255\n\0\0\0\xff\xff\xff".to_vec(), ppm);
+
+        let mut png_bytes = Vec::new();
+        image.write_png(&mut png_bytes).unwrap();
+        let mut reader = png::Decoder::new(png_bytes.as_slice()).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+        assert_eq!((2, 1), (info.width, info.height));
+        assert_eq!(vec![0, 0, 0, 255, 255, 255], decoded);
+    }
+}
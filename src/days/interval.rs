@@ -0,0 +1,486 @@
+//! Inclusive integer intervals, N-dimensional boxes built from them, and normalized sets of both.
+
+use crate::days::geometry::Coord;
+use std::fmt;
+
+/// The integers from `start` to `end`, both included, so `x=10..12` has length 3. Never empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Interval<T> {
+    start: T,
+    end: T,
+}
+
+impl<T: Coord> Interval<T> {
+    /// `start..=end`, or `None` if `start > end`.
+    pub fn new(start: T, end: T) -> Option<Interval<T>> {
+        if start <= end {
+            Some(Interval { start, end })
+        } else {
+            None
+        }
+    }
+
+    pub fn single(value: T) -> Interval<T> {
+        Interval {
+            start: value,
+            end: value,
+        }
+    }
+
+    pub fn start(&self) -> T {
+        self.start
+    }
+
+    pub fn end(&self) -> T {
+        self.end
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.start <= value && value <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval<T>) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    /// Whether the two intervals overlap or one ends right before the other starts.
+    fn touches(&self, other: &Interval<T>) -> bool {
+        // other.start > self.end, so subtracting one can't overflow (and likewise the other way)
+        self.overlaps(other)
+            || (other.start > self.end && other.start - T::ONE == self.end)
+            || (self.start > other.end && self.start - T::ONE == other.end)
+    }
+
+    pub fn intersection(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        Interval::new(self.start.max(other.start), self.end.min(other.end))
+    }
+
+    /// The interval covering both, if there's no gap between them.
+    pub fn union(&self, other: &Interval<T>) -> Option<Interval<T>> {
+        if self.touches(other) {
+            Interval::new(self.start.min(other.start), self.end.max(other.end))
+        } else {
+            None
+        }
+    }
+
+    /// What's left of this interval without `other`: nothing, one piece or two, in order.
+    pub fn subtract(&self, other: &Interval<T>) -> Vec<Interval<T>> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+
+        let mut pieces = Vec::new();
+        if self.start < other.start {
+            pieces.push(Interval {
+                start: self.start,
+                end: other.start - T::ONE,
+            });
+        }
+        if other.end < self.end {
+            pieces.push(Interval {
+                start: other.end + T::ONE,
+                end: self.end,
+            });
+        }
+        pieces
+    }
+}
+
+impl<T: Coord> Interval<T>
+where
+    u128: TryFrom<T>,
+{
+    /// How many integers the interval holds, always at least one.
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u128 {
+        let width = u128::try_from(self.end - self.start)
+            .unwrap_or_else(|_| panic!("Interval {:?} is too wide", self));
+        width + 1
+    }
+}
+
+impl<T: fmt::Display> fmt::Display for Interval<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// Merges overlapping and adjacent intervals, returning what's left sorted by start.
+pub fn coalesce<T: Coord>(intervals: impl IntoIterator<Item = Interval<T>>) -> Vec<Interval<T>> {
+    let mut sorted: Vec<Interval<T>> = intervals.into_iter().collect();
+    sorted.sort();
+
+    let mut merged: Vec<Interval<T>> = Vec::with_capacity(sorted.len());
+    for interval in sorted {
+        match merged.last_mut().and_then(|last| last.union(&interval)) {
+            Some(union) => *merged.last_mut().unwrap() = union,
+            None => merged.push(interval),
+        }
+    }
+    merged
+}
+
+/// A set of integers stored as sorted intervals with gaps between them, so two sets holding the
+/// same integers are always equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct IntervalSet<T> {
+    intervals: Vec<Interval<T>>,
+}
+
+impl<T: Coord> IntervalSet<T> {
+    pub fn new() -> IntervalSet<T> {
+        IntervalSet {
+            intervals: Vec::new(),
+        }
+    }
+
+    pub fn intervals(&self) -> &[Interval<T>] {
+        &self.intervals
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.intervals.is_empty()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        // the first interval ending at or after value is the only one that can hold it
+        let index = self.intervals.partition_point(|it| it.end < value);
+        self.intervals
+            .get(index)
+            .is_some_and(|it| it.contains(value))
+    }
+
+    pub fn insert(&mut self, interval: Interval<T>) {
+        let mut intervals = std::mem::take(&mut self.intervals);
+        intervals.push(interval);
+        self.intervals = coalesce(intervals);
+    }
+
+    pub fn remove(&mut self, interval: &Interval<T>) {
+        self.intervals = self
+            .intervals
+            .iter()
+            .flat_map(|it| it.subtract(interval))
+            .collect();
+    }
+
+    pub fn union(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        self.intervals
+            .iter()
+            .chain(other.intervals.iter())
+            .copied()
+            .collect()
+    }
+
+    pub fn intersection(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut intervals = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            intervals.extend(a.intersection(&b));
+            // whichever ends first can't overlap anything further along the other set
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals }
+    }
+
+    pub fn difference(&self, other: &IntervalSet<T>) -> IntervalSet<T> {
+        let mut difference = self.clone();
+        for interval in &other.intervals {
+            difference.remove(interval);
+        }
+        difference
+    }
+}
+
+impl<T: Coord> IntervalSet<T>
+where
+    u128: TryFrom<T>,
+{
+    /// How many integers the set holds.
+    pub fn len(&self) -> u128 {
+        self.intervals.iter().map(|it| it.len()).sum()
+    }
+}
+
+impl<T: Coord> Default for IntervalSet<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: Coord> FromIterator<Interval<T>> for IntervalSet<T> {
+    fn from_iter<I: IntoIterator<Item = Interval<T>>>(iter: I) -> Self {
+        IntervalSet {
+            intervals: coalesce(iter),
+        }
+    }
+}
+
+/// An axis-aligned box in `N` dimensions: every point whose `i`-th coordinate is in `axes[i]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Cuboid<T, const N: usize> {
+    pub axes: [Interval<T>; N],
+}
+
+impl<T: Coord, const N: usize> Cuboid<T, N> {
+    pub fn new(axes: [Interval<T>; N]) -> Cuboid<T, N> {
+        Cuboid { axes }
+    }
+
+    pub fn contains(&self, point: [T; N]) -> bool {
+        self.axes
+            .iter()
+            .zip(point)
+            .all(|(axis, coord)| axis.contains(coord))
+    }
+
+    pub fn overlaps(&self, other: &Cuboid<T, N>) -> bool {
+        self.axes
+            .iter()
+            .zip(other.axes.iter())
+            .all(|(a, b)| a.overlaps(b))
+    }
+
+    pub fn intersection(&self, other: &Cuboid<T, N>) -> Option<Cuboid<T, N>> {
+        let mut axes = self.axes;
+        for (axis, other_axis) in axes.iter_mut().zip(other.axes.iter()) {
+            *axis = axis.intersection(other_axis)?;
+        }
+        Some(Cuboid { axes })
+    }
+
+    /// What's left of this box without `other`, as at most `2 * N` disjoint boxes.
+    pub fn subtract(&self, other: &Cuboid<T, N>) -> Vec<Cuboid<T, N>> {
+        if !self.overlaps(other) {
+            return vec![*self];
+        }
+
+        // peel off the slabs outside `other` one axis at a time, shrinking what's left to the
+        // overlap on that axis
+        let mut pieces = Vec::new();
+        let mut remaining = *self;
+        for i in 0..N {
+            for slab in remaining.axes[i].subtract(&other.axes[i]) {
+                let mut piece = remaining;
+                piece.axes[i] = slab;
+                pieces.push(piece);
+            }
+            remaining.axes[i] = remaining.axes[i].intersection(&other.axes[i]).unwrap();
+        }
+        pieces
+    }
+
+    /// Both boxes as disjoint boxes: this one, plus the parts of `other` outside it.
+    pub fn union(&self, other: &Cuboid<T, N>) -> Vec<Cuboid<T, N>> {
+        let mut boxes = vec![*self];
+        boxes.extend(other.subtract(self));
+        boxes
+    }
+}
+
+impl<T: Coord, const N: usize> Cuboid<T, N>
+where
+    u128: TryFrom<T>,
+{
+    /// How many integer points the box holds.
+    pub fn volume(&self) -> u128 {
+        self.axes.iter().map(|axis| axis.len()).product()
+    }
+}
+
+/// A set of integer points in `N` dimensions, stored as disjoint boxes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CuboidSet<T, const N: usize> {
+    cuboids: Vec<Cuboid<T, N>>,
+}
+
+impl<T: Coord, const N: usize> CuboidSet<T, N> {
+    pub fn new() -> CuboidSet<T, N> {
+        CuboidSet {
+            cuboids: Vec::new(),
+        }
+    }
+
+    pub fn cuboids(&self) -> &[Cuboid<T, N>] {
+        &self.cuboids
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cuboids.is_empty()
+    }
+
+    pub fn contains(&self, point: [T; N]) -> bool {
+        self.cuboids.iter().any(|it| it.contains(point))
+    }
+
+    pub fn insert(&mut self, cuboid: Cuboid<T, N>) {
+        self.remove(&cuboid);
+        self.cuboids.push(cuboid);
+    }
+
+    pub fn remove(&mut self, cuboid: &Cuboid<T, N>) {
+        self.cuboids = self
+            .cuboids
+            .iter()
+            .flat_map(|it| it.subtract(cuboid))
+            .collect();
+    }
+
+    pub fn intersection(&self, cuboid: &Cuboid<T, N>) -> CuboidSet<T, N> {
+        CuboidSet {
+            cuboids: self
+                .cuboids
+                .iter()
+                .filter_map(|it| it.intersection(cuboid))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Coord, const N: usize> CuboidSet<T, N>
+where
+    u128: TryFrom<T>,
+{
+    /// How many integer points the set holds.
+    pub fn volume(&self) -> u128 {
+        self.cuboids.iter().map(|it| it.volume()).sum()
+    }
+}
+
+impl<T: Coord, const N: usize> Default for CuboidSet<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::interval::{coalesce, Cuboid, CuboidSet, Interval, IntervalSet};
+
+    fn interval(start: i32, end: i32) -> Interval<i32> {
+        Interval::new(start, end).unwrap()
+    }
+
+    fn cube(start: i32, end: i32) -> Cuboid<i32, 3> {
+        Cuboid::new([interval(start, end); 3])
+    }
+
+    #[test]
+    fn test_interval_ops() {
+        let a = interval(-5, 10);
+        assert_eq!(16, a.len());
+        assert_eq!(None, Interval::new(3, 2));
+
+        assert_eq!(Some(interval(3, 10)), a.intersection(&interval(3, 20)));
+        assert_eq!(None, a.intersection(&interval(11, 20)));
+        assert_eq!(Some(interval(-5, 20)), a.union(&interval(11, 20)));
+        assert_eq!(None, a.union(&interval(12, 20)));
+
+        assert_eq!(
+            vec![interval(-5, -1), interval(4, 10)],
+            a.subtract(&interval(0, 3))
+        );
+        assert_eq!(vec![interval(-5, 0)], a.subtract(&interval(1, 30)));
+        assert_eq!(Vec::<Interval<i32>>::new(), a.subtract(&interval(-9, 10)));
+        assert_eq!(vec![a], a.subtract(&interval(11, 12)));
+    }
+
+    #[test]
+    fn test_extreme_bounds() {
+        let top = Interval::new(u8::MAX - 1, u8::MAX).unwrap();
+        let bottom = Interval::new(0, 1).unwrap();
+        assert_eq!(None, top.union(&bottom));
+        assert_eq!(
+            Some(Interval::new(0, u8::MAX).unwrap()),
+            top.union(&Interval::new(0, 253).unwrap())
+        );
+        assert_eq!(256, Interval::new(0, u8::MAX).unwrap().len());
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let merged = coalesce([
+            interval(8, 9),
+            interval(1, 3),
+            interval(4, 5),
+            interval(2, 2),
+        ]);
+        assert_eq!(vec![interval(1, 5), interval(8, 9)], merged);
+    }
+
+    #[test]
+    fn test_interval_set_is_normalized() {
+        let mut set: IntervalSet<i32> = [interval(10, 20), interval(0, 4)].into_iter().collect();
+        set.insert(interval(5, 9));
+        assert_eq!(&[interval(0, 20)], set.intervals());
+
+        set.remove(&interval(8, 12));
+        assert_eq!(&[interval(0, 7), interval(13, 20)], set.intervals());
+        assert_eq!(16, set.len());
+        assert!(set.contains(7) && !set.contains(8) && set.contains(13));
+
+        // the same integers built a different way give an equal set
+        let rebuilt: IntervalSet<i32> = (0..=20)
+            .filter(|&i| !(8..=12).contains(&i))
+            .map(Interval::single)
+            .collect();
+        assert_eq!(set, rebuilt);
+    }
+
+    #[test]
+    fn test_interval_set_algebra() {
+        let a: IntervalSet<i32> = [interval(0, 10), interval(20, 30)].into_iter().collect();
+        let b: IntervalSet<i32> = [interval(5, 25), interval(40, 41)].into_iter().collect();
+
+        assert_eq!(
+            &[interval(0, 30), interval(40, 41)],
+            a.union(&b).intervals()
+        );
+        assert_eq!(
+            &[interval(5, 10), interval(20, 25)],
+            a.intersection(&b).intervals()
+        );
+        assert_eq!(
+            &[interval(0, 4), interval(26, 30)],
+            a.difference(&b).intervals()
+        );
+    }
+
+    #[test]
+    fn test_cuboid_ops() {
+        let a = cube(0, 2);
+        let b = cube(1, 3);
+        assert_eq!(27, a.volume());
+        assert_eq!(Some(cube(1, 2)), a.intersection(&b));
+        assert_eq!(None, a.intersection(&cube(3, 4)));
+
+        let rest = a.subtract(&b);
+        assert_eq!(19, rest.iter().map(|it| it.volume()).sum::<u128>());
+        assert!(rest.iter().all(|it| it.intersection(&b).is_none()));
+        assert_eq!(46, a.union(&b).iter().map(|it| it.volume()).sum::<u128>());
+    }
+
+    #[test]
+    fn test_cuboid_set_reboot_steps() {
+        // the small reboot example from Day 22
+        let cuboid = |x: (i32, i32), y: (i32, i32), z: (i32, i32)| {
+            Cuboid::new([interval(x.0, x.1), interval(y.0, y.1), interval(z.0, z.1)])
+        };
+        let mut on: CuboidSet<i32, 3> = CuboidSet::new();
+        on.insert(cuboid((10, 12), (10, 12), (10, 12)));
+        assert_eq!(27, on.volume());
+        on.insert(cuboid((11, 13), (11, 13), (11, 13)));
+        assert_eq!(46, on.volume());
+        on.remove(&cuboid((9, 11), (9, 11), (9, 11)));
+        assert_eq!(38, on.volume());
+        on.insert(cuboid((10, 10), (10, 10), (10, 10)));
+        assert_eq!(39, on.volume());
+        assert!(on.contains([10, 10, 10]) && !on.contains([11, 11, 10]));
+    }
+}
@@ -0,0 +1,365 @@
+//! Square transition matrices for counting linear recurrences (lanternfish, polymers) in
+//! O(log n) steps.
+
+use num_bigint::BigUint;
+
+/// A square matrix of counts, used as the transition matrix of a linear recurrence: if `v[j]` is
+/// how many things are in state `j`, then after one step `(M * v)[i]` are in state `i`. Raising
+/// `M` to the `n`-th power by repeated squaring answers `n` steps in `O(size³ log n)`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Matrix {
+    size: usize,
+    values: Vec<u128>,
+}
+
+impl Matrix {
+    pub fn zero(size: usize) -> Matrix {
+        Matrix {
+            size,
+            values: vec![0; size * size],
+        }
+    }
+
+    pub fn identity(size: usize) -> Matrix {
+        let mut m = Matrix::zero(size);
+        for i in 0..size {
+            m.set(i, i, 1);
+        }
+        m
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> u128 {
+        self.values[row * self.size + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: u128) {
+        self.values[row * self.size + col] = value;
+    }
+
+    /// Records that each thing in state `from` becomes `count` more things in state `to`.
+    pub fn add_transition(&mut self, from: usize, to: usize, count: u128) {
+        self.values[to * self.size + from] += count;
+    }
+
+    /// `self * other`, or `None` if any entry overflows `u128`.
+    pub fn checked_mul(&self, other: &Matrix) -> Option<Matrix> {
+        self.mul_with(other, |acc, a, b| acc.checked_add(a.checked_mul(b)?))
+    }
+
+    /// `self * other` with every entry reduced modulo `modulus`.
+    pub fn mul_mod(&self, other: &Matrix, modulus: u128) -> Matrix {
+        self.mul_with(other, |acc, a, b| {
+            Some(add_mod(acc, mul_mod(a, b, modulus), modulus))
+        })
+        .unwrap()
+    }
+
+    fn mul_with(
+        &self,
+        other: &Matrix,
+        multiply_add: impl Fn(u128, u128, u128) -> Option<u128>,
+    ) -> Option<Matrix> {
+        assert_eq!(self.size, other.size, "Matrix sizes differ");
+        let mut result = Matrix::zero(self.size);
+        for row in 0..self.size {
+            for k in 0..self.size {
+                let a = self.get(row, k);
+                if a == 0 {
+                    continue;
+                }
+                for col in 0..self.size {
+                    let index = row * self.size + col;
+                    result.values[index] =
+                        multiply_add(result.values[index], a, other.get(k, col))?;
+                }
+            }
+        }
+        Some(result)
+    }
+
+    /// `self` to the power `n`, or `None` if any entry overflows `u128` along the way.
+    pub fn checked_pow(&self, n: u64) -> Option<Matrix> {
+        self.pow_with(n, |a, b| a.checked_mul(b))
+    }
+
+    /// `self` to the power `n` with every entry reduced modulo `modulus`.
+    pub fn pow_mod(&self, n: u64, modulus: u128) -> Matrix {
+        self.pow_with(n, |a, b| Some(a.mul_mod(b, modulus)))
+            .unwrap()
+    }
+
+    fn pow_with(
+        &self,
+        n: u64,
+        multiply: impl Fn(&Matrix, &Matrix) -> Option<Matrix>,
+    ) -> Option<Matrix> {
+        let mut result = Matrix::identity(self.size);
+        let mut base = self.clone();
+        let mut remaining = n;
+        while remaining > 0 {
+            if remaining & 1 == 1 {
+                result = multiply(&result, &base)?;
+            }
+            remaining >>= 1;
+            // skip the final squaring, which isn't needed and might overflow
+            if remaining > 0 {
+                base = multiply(&base, &base)?;
+            }
+        }
+        Some(result)
+    }
+
+    /// `self * counts`, or `None` if any entry overflows `u128`.
+    pub fn checked_apply(&self, counts: &[u128]) -> Option<Vec<u128>> {
+        assert_eq!(
+            self.size,
+            counts.len(),
+            "Vector size differs from matrix size"
+        );
+        (0..self.size)
+            .map(|row| {
+                counts
+                    .iter()
+                    .enumerate()
+                    .try_fold(0u128, |acc, (col, &count)| {
+                        acc.checked_add(self.get(row, col).checked_mul(count)?)
+                    })
+            })
+            .collect()
+    }
+
+    /// `self * counts` with every entry reduced modulo `modulus`.
+    pub fn apply_mod(&self, counts: &[u128], modulus: u128) -> Vec<u128> {
+        assert_eq!(
+            self.size,
+            counts.len(),
+            "Vector size differs from matrix size"
+        );
+        (0..self.size)
+            .map(|row| {
+                counts.iter().enumerate().fold(0, |acc, (col, &count)| {
+                    add_mod(acc, mul_mod(self.get(row, col), count, modulus), modulus)
+                })
+            })
+            .collect()
+    }
+
+    /// The counts after `steps` steps starting from `counts`, or `None` on overflow.
+    pub fn checked_counts_after(&self, counts: &[u128], steps: u64) -> Option<Vec<u128>> {
+        self.checked_pow(steps)?.checked_apply(counts)
+    }
+
+    /// The counts after `steps` steps starting from `counts`, modulo `modulus`.
+    pub fn counts_after_mod(&self, counts: &[u128], steps: u64, modulus: u128) -> Vec<u128> {
+        self.pow_mod(steps, modulus).apply_mod(counts, modulus)
+    }
+
+    /// The natural logarithm of each count after `steps` steps starting from `counts`, for when
+    /// the counts are far too big to hold. Negative infinity stands for a count of zero.
+    pub fn ln_counts_after(&self, counts: &[u128], steps: u64) -> Vec<f64> {
+        let to_ln =
+            |values: &[u128]| -> Vec<f64> { values.iter().map(|&v| (v as f64).ln()).collect() };
+        let size = self.size;
+        let ln_mul = |a: &[f64], b: &[f64], columns: usize| -> Vec<f64> {
+            (0..size * columns)
+                .map(|index| {
+                    let (row, col) = (index / columns, index % columns);
+                    ln_sum((0..size).map(|k| a[row * size + k] + b[k * columns + col]))
+                })
+                .collect()
+        };
+
+        let power = square_and_multiply(
+            to_ln(&Matrix::identity(size).values),
+            to_ln(&self.values),
+            steps,
+            |a, b| ln_mul(a, b, size),
+        );
+        ln_mul(&power, &to_ln(counts), 1)
+    }
+
+    /// The exact counts after `steps` steps starting from `counts`, however big they get.
+    pub fn big_counts_after(&self, counts: &[BigUint], steps: u64) -> Vec<BigUint> {
+        assert_eq!(
+            self.size,
+            counts.len(),
+            "Vector size differs from matrix size"
+        );
+        let to_big = |values: &[u128]| -> Vec<BigUint> {
+            values.iter().map(|&v| BigUint::from(v)).collect()
+        };
+        let size = self.size;
+        let big_mul = |a: &[BigUint], b: &[BigUint], columns: usize| -> Vec<BigUint> {
+            (0..size * columns)
+                .map(|index| {
+                    let (row, col) = (index / columns, index % columns);
+                    (0..size)
+                        .map(|k| &a[row * size + k] * &b[k * columns + col])
+                        .sum()
+                })
+                .collect()
+        };
+
+        let power = square_and_multiply(
+            to_big(&Matrix::identity(size).values),
+            to_big(&self.values),
+            steps,
+            |a, b| big_mul(a, b, size),
+        );
+        big_mul(&power, counts, 1)
+    }
+}
+
+/// `base` to the power `n` by repeated squaring, starting from `identity`.
+fn square_and_multiply<T>(identity: T, base: T, n: u64, multiply: impl Fn(&T, &T) -> T) -> T {
+    let mut result = identity;
+    let mut base = base;
+    let mut remaining = n;
+    while remaining > 0 {
+        if remaining & 1 == 1 {
+            result = multiply(&result, &base);
+        }
+        remaining >>= 1;
+        // skip the final squaring, which isn't needed
+        if remaining > 0 {
+            base = multiply(&base, &base);
+        }
+    }
+    result
+}
+
+/// `ln(Σ e^x)` over `values`, scaled by the largest so the exponentials can't overflow.
+pub fn ln_sum(values: impl Iterator<Item = f64>) -> f64 {
+    let values: Vec<f64> = values.collect();
+    let largest = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if largest == f64::NEG_INFINITY {
+        return largest;
+    }
+    largest + values.iter().map(|v| (v - largest).exp()).sum::<f64>().ln()
+}
+
+/// `a * b % modulus` without overflowing, by shift-and-add when the product doesn't fit.
+fn mul_mod(a: u128, b: u128, modulus: u128) -> u128 {
+    let (a, mut b) = (a % modulus, b % modulus);
+    if let Some(product) = a.checked_mul(b) {
+        return product % modulus;
+    }
+
+    let mut result = 0;
+    let mut addend = a;
+    while b > 0 {
+        if b & 1 == 1 {
+            result = add_mod(result, addend, modulus);
+        }
+        addend = add_mod(addend, addend, modulus);
+        b >>= 1;
+    }
+    result
+}
+
+fn add_mod(a: u128, b: u128, modulus: u128) -> u128 {
+    // a, b < modulus, so a + b only overflows if the true sum is >= modulus
+    match a.checked_add(b) {
+        Some(sum) => sum % modulus,
+        None => a - (modulus - b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::matrix::Matrix;
+    use num_bigint::BigUint;
+
+    fn fibonacci() -> Matrix {
+        // state 0 = F(n), state 1 = F(n - 1)
+        let mut m = Matrix::zero(2);
+        m.add_transition(0, 0, 1);
+        m.add_transition(1, 0, 1);
+        m.add_transition(0, 1, 1);
+        m
+    }
+
+    #[test]
+    fn test_pow_matches_stepping() {
+        let m = fibonacci();
+        let mut counts = vec![1, 0];
+        for n in 0..50 {
+            assert_eq!(Some(counts.clone()), m.checked_counts_after(&[1, 0], n));
+            counts = m.checked_apply(&counts).unwrap();
+        }
+        assert_eq!(
+            Some(vec![12586269025, 7778742049]),
+            m.checked_counts_after(&[1, 0], 49)
+        );
+    }
+
+    #[test]
+    fn test_overflow_is_reported() {
+        // F(186) is the largest Fibonacci number that fits in a u128
+        let m = fibonacci();
+        assert!(m.checked_counts_after(&[1, 0], 185).is_some());
+        assert_eq!(None, m.checked_counts_after(&[1, 0], 186));
+    }
+
+    #[test]
+    fn test_ln_counts_after() {
+        let m = fibonacci();
+        let exact = m.checked_counts_after(&[1, 0], 150).unwrap();
+        let ln = m.ln_counts_after(&[1, 0], 150);
+        for (count, ln) in exact.iter().zip(ln) {
+            assert!(((*count as f64).ln() - ln).abs() < 1e-9);
+        }
+
+        // F(n) grows like φ^n / √5
+        let golden_ratio: f64 = (1.0 + 5f64.sqrt()) / 2.0;
+        let ln = m.ln_counts_after(&[1, 0], 1_000_000);
+        let expected = 1_000_001.0 * golden_ratio.ln() - 5f64.sqrt().ln();
+        assert!((ln[0] - expected).abs() / expected < 1e-12);
+        assert_eq!(f64::NEG_INFINITY, m.ln_counts_after(&[0, 0], 10)[0]);
+    }
+
+    #[test]
+    fn test_big_counts_after() {
+        let m = fibonacci();
+        let exact = m.checked_counts_after(&[1, 0], 185).unwrap();
+        let big = big_after(&m, 185);
+        assert_eq!(
+            exact.into_iter().map(BigUint::from).collect::<Vec<_>>(),
+            big
+        );
+
+        // F(n + 1) = F(n) + F(n - 1) well past what a u128 holds
+        let next = big_after(&m, 1001);
+        assert_eq!(next[0], &big_after(&m, 1000)[0] + &big_after(&m, 999)[0]);
+        assert!(next[0].bits() > 600);
+    }
+
+    fn big_after(m: &Matrix, steps: u64) -> Vec<BigUint> {
+        m.big_counts_after(&[BigUint::from(1u32), BigUint::default()], steps)
+    }
+
+    #[test]
+    fn test_mod_matches_checked() {
+        let m = fibonacci();
+        let modulus = 1_000_000_007;
+        let exact = m.checked_counts_after(&[1, 0], 150).unwrap();
+        let reduced = m.counts_after_mod(&[1, 0], 150, modulus);
+        assert_eq!(
+            exact.iter().map(|c| c % modulus).collect::<Vec<_>>(),
+            reduced
+        );
+
+        // a modulus near u128::MAX exercises the shift-and-add multiplication
+        let big_modulus = u128::MAX - 158;
+        let exact = m.checked_counts_after(&[1, 0], 185).unwrap();
+        let reduced = m.counts_after_mod(&[1, 0], 185, big_modulus);
+        assert_eq!(
+            exact.iter().map(|c| c % big_modulus).collect::<Vec<_>>(),
+            reduced
+        );
+    }
+}
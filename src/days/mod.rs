@@ -1,3 +1,6 @@
+//! The solution for each day, and the toolkit they share.
+
+pub mod counter;
 pub mod day01;
 pub mod day02;
 pub mod day03;
@@ -17,5 +20,13 @@ pub mod day16;
 pub mod day17;
 pub mod day18;
 pub mod day21;
-mod graph;
+pub mod geometry;
+pub mod graph;
+pub mod image;
+pub mod interval;
+pub mod matrix;
+pub mod parse;
+pub mod search;
+pub mod simulation;
 pub mod template;
+pub mod union_find;
@@ -0,0 +1,370 @@
+//! Input parsing helpers that report errors with the line and column they were found at.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Malformed input, pointing at the 1-based line and column where the problem starts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+pub type ParseResult<T> = Result<T, ParseError>;
+
+/// A piece of puzzle input that remembers where it starts in the original text, so anything
+/// split or parsed out of it can report an exact position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span<'a> {
+    pub text: &'a str,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl<'a> Span<'a> {
+    pub fn new(text: &'a str) -> Span<'a> {
+        Span {
+            text,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// The part of this span between byte offsets `start` and `end`.
+    pub fn slice(&self, start: usize, end: usize) -> Span<'a> {
+        let rest = self.advance(start);
+        Span {
+            text: &rest.text[..end - start],
+            ..rest
+        }
+    }
+
+    /// The rest of this span after its first `count` bytes. Only those bytes are scanned, so
+    /// walking through a span a piece at a time takes time linear in its length.
+    pub fn advance(&self, count: usize) -> Span<'a> {
+        let consumed = &self.text[..count];
+        #[cfg(test)]
+        tests::BYTES_SCANNED.with(|scanned| scanned.set(scanned.get() + count));
+        let (line, column) = match consumed.rfind('\n') {
+            Some(newline) => (
+                self.line + consumed.matches('\n').count(),
+                consumed[newline + 1..].chars().count() + 1,
+            ),
+            None => (self.line, self.column + consumed.chars().count()),
+        };
+        Span {
+            text: &self.text[count..],
+            line,
+            column,
+        }
+    }
+
+    pub fn trim(&self) -> Span<'a> {
+        let start = self.text.len() - self.text.trim_start().len();
+        let end = usize::max(start, self.text.trim_end().len());
+        self.slice(start, end)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = Span<'a>> {
+        let text = self.text.strip_suffix('\n').unwrap_or(self.text);
+        Span { text, ..*self }.split("\n").map(|line| Span {
+            text: line.text.trim_end_matches('\r'),
+            ..line
+        })
+    }
+
+    pub fn split(&self, separator: &'a str) -> impl Iterator<Item = Span<'a>> {
+        assert!(!separator.is_empty(), "Separator must not be empty");
+        let mut rest = Some(*self);
+        std::iter::from_fn(move || {
+            let current = rest?;
+            match current.text.find(separator) {
+                Some(index) => {
+                    rest = Some(current.advance(index + separator.len()));
+                    Some(Span {
+                        text: &current.text[..index],
+                        ..current
+                    })
+                }
+                None => rest.take(),
+            }
+        })
+    }
+
+    pub fn split_whitespace(&self) -> impl Iterator<Item = Span<'a>> {
+        let mut rest = *self;
+        self.text.split_whitespace().map(move |word| {
+            let start = rest.advance(rest.offset_of(word));
+            rest = start.advance(word.len());
+            Span {
+                text: word,
+                ..start
+            }
+        })
+    }
+
+    pub fn split_once(&self, separator: &str) -> Option<(Span<'a>, Span<'a>)> {
+        self.text.find(separator).map(|index| {
+            (
+                self.slice(0, index),
+                self.slice(index + separator.len(), self.text.len()),
+            )
+        })
+    }
+
+    pub fn strip_prefix(&self, prefix: &str) -> Option<Span<'a>> {
+        if self.text.starts_with(prefix) {
+            Some(self.slice(prefix.len(), self.text.len()))
+        } else {
+            None
+        }
+    }
+
+    pub fn parse<T>(&self) -> ParseResult<T>
+    where
+        T: FromStr,
+        T::Err: fmt::Display,
+    {
+        self.text
+            .parse::<T>()
+            .map_err(|e| self.error(format!("cannot parse `{}`: {}", self.text, e)))
+    }
+
+    pub fn error(&self, message: impl Into<String>) -> ParseError {
+        ParseError {
+            line: self.line,
+            column: self.column,
+            message: message.into(),
+        }
+    }
+
+    /// The byte offset of `sub`, which must be a subslice of this span's text.
+    fn offset_of(&self, sub: &str) -> usize {
+        sub.as_ptr() as usize - self.text.as_ptr() as usize
+    }
+}
+
+/// Numbers separated by commas, e.g. `3,4,3,1,2`.
+pub fn comma_separated<T>(span: Span) -> ParseResult<Vec<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    span.trim().split(",").map(|it| it.trim().parse()).collect()
+}
+
+/// Numbers separated by any amount of whitespace, including newlines.
+pub fn whitespace_separated<T>(span: Span) -> ParseResult<Vec<T>>
+where
+    T: FromStr,
+    T::Err: fmt::Display,
+{
+    span.split_whitespace().map(|it| it.parse()).collect()
+}
+
+/// The trimmed, non-empty blocks of lines between blank lines.
+pub fn blocks<'a>(span: Span<'a>) -> Vec<Span<'a>> {
+    let mut blocks = Vec::new();
+    // the first line of the block being collected, and the byte offset where the block ends
+    let mut block: Option<(Span, usize)> = None;
+    let finish = |(first, end): (Span<'a>, usize)| {
+        Span {
+            text: &span.text[span.offset_of(first.text)..end],
+            ..first
+        }
+        .trim()
+    };
+
+    for line in span.lines() {
+        if line.text.trim().is_empty() {
+            if let Some(block) = block.take() {
+                blocks.push(finish(block));
+            }
+        } else {
+            let first = block.map_or(line, |(first, _)| first);
+            block = Some((first, span.offset_of(line.text) + line.text.len()));
+        }
+    }
+    if let Some(block) = block {
+        blocks.push(finish(block));
+    }
+
+    blocks
+}
+
+/// A rectangular grid of single decimal digits, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigitGrid {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<u32>,
+}
+
+/// A rectangular block of digits like `2199943210`, one row per line.
+pub fn digit_grid(span: Span) -> ParseResult<DigitGrid> {
+    let trimmed = span.trim();
+    if trimmed.is_empty() {
+        return Err(trimmed.error("expected a grid of digits"));
+    }
+
+    let mut values = Vec::new();
+    let mut width = None;
+    let mut height = 0;
+    for line in trimmed.lines() {
+        let line = line.trim();
+        for (i, ch) in line.text.chars().enumerate() {
+            match ch.to_digit(10) {
+                Some(digit) => values.push(digit),
+                None => {
+                    let at = Span {
+                        column: line.column + i,
+                        ..line
+                    };
+                    return Err(at.error(format!("expected a digit, found `{}`", ch)));
+                }
+            }
+        }
+
+        let line_width = line.text.chars().count();
+        match width {
+            None => width = Some(line_width),
+            Some(w) if w != line_width => {
+                return Err(line.error(format!(
+                    "expected a row of {} digits, found {}",
+                    w, line_width
+                )))
+            }
+            _ => {}
+        }
+        height += 1;
+    }
+
+    Ok(DigitGrid {
+        width: width.unwrap(),
+        height,
+        values,
+    })
+}
+
+/// Non-empty lines of the form `key -> value`, each side trimmed.
+pub fn rule_lines(span: Span) -> ParseResult<Vec<(Span, Span)>> {
+    span.trim()
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .map(|line| match line.split_once(" -> ") {
+            Some((key, value)) if !key.trim().is_empty() && !value.trim().is_empty() => {
+                Ok((key.trim(), value.trim()))
+            }
+            _ => Err(line.error(format!("expected `key -> value`, found `{}`", line.text))),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::parse::*;
+    use std::cell::Cell;
+
+    thread_local! {
+        /// How many bytes [`Span::advance`] has scanned on this thread.
+        pub static BYTES_SCANNED: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// How many bytes `parse` scans to work out positions.
+    fn bytes_scanned<T>(parse: impl FnOnce() -> T) -> (T, usize) {
+        let before = BYTES_SCANNED.with(Cell::get);
+        let result = parse();
+        (result, BYTES_SCANNED.with(Cell::get) - before)
+    }
+
+    #[test]
+    fn test_comma_separated() {
+        assert_eq!(
+            Ok(vec![3, 4, 3, 1, 2]),
+            comma_separated::<u8>(Span::new("3,4,3,1,2\n"))
+        );
+
+        let err = comma_separated::<u8>(Span::new("3,4,x,1")).unwrap_err();
+        assert_eq!((1, 5), (err.line, err.column));
+    }
+
+    #[test]
+    fn test_whitespace_separated() {
+        let err = whitespace_separated::<i32>(Span::new("22 13\n 8  2 2x")).unwrap_err();
+        assert_eq!((2, 7), (err.line, err.column));
+        assert!(err.message.contains("`2x`"));
+    }
+
+    #[test]
+    fn test_blocks_keep_positions() {
+        let input = "\nNNCB\n\n\nCH -> B\n  HH -> N\n\n";
+        let blocks = blocks(Span::new(input));
+        assert_eq!(2, blocks.len());
+        assert_eq!(
+            ("NNCB", 2, 1),
+            (blocks[0].text, blocks[0].line, blocks[0].column)
+        );
+        assert_eq!(
+            ("CH -> B\n  HH -> N", 5, 1),
+            (blocks[1].text, blocks[1].line, blocks[1].column)
+        );
+    }
+
+    #[test]
+    fn test_a_million_lines_in_linear_time() {
+        let input = "12 34\n\n".repeat(500_000);
+
+        // each byte is scanned once for its position, give or take the separators
+        let (last, scanned) = bytes_scanned(|| Span::new(&input).lines().last().unwrap());
+        assert_eq!(1_000_000, last.line);
+        assert!(scanned <= input.len());
+        let (numbers, scanned) =
+            bytes_scanned(|| whitespace_separated::<u32>(Span::new(&input)).unwrap());
+        assert_eq!(1_000_000, numbers.len());
+        assert!(scanned <= input.len());
+        let (blocks, scanned) = bytes_scanned(|| blocks(Span::new(&input)));
+        assert_eq!((500_000, 999_999), (blocks.len(), blocks[499_999].line));
+        assert!(scanned <= 2 * input.len());
+    }
+
+    #[test]
+    fn test_digit_grid() {
+        let grid = digit_grid(Span::new("219\n398\n")).unwrap();
+        assert_eq!((3, 2), (grid.width, grid.height));
+        assert_eq!(vec![2, 1, 9, 3, 9, 8], grid.values);
+
+        let err = digit_grid(Span::new("219\n3a8")).unwrap_err();
+        assert_eq!((2, 2), (err.line, err.column));
+
+        let err = digit_grid(Span::new("219\n39")).unwrap_err();
+        assert_eq!((2, 1), (err.line, err.column));
+    }
+
+    #[test]
+    fn test_rule_lines() {
+        let rules = rule_lines(Span::new("CH -> B\nHH -> N")).unwrap();
+        assert_eq!(("HH", "N"), (rules[1].0.text, rules[1].1.text));
+        assert_eq!((2, 7), (rules[1].1.line, rules[1].1.column));
+
+        let err = rule_lines(Span::new("CH -> B\nHH => N")).unwrap_err();
+        assert_eq!((2, 1), (err.line, err.column));
+    }
+}
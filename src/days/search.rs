@@ -0,0 +1,261 @@
+//! Dijkstra, A* and breadth-first search over any state type.
+
+use std::cmp::Reverse;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap, VecDeque};
+use std::fmt::Debug;
+use std::hash::Hash;
+use std::ops::Add;
+
+/// A path cost: non-negative and additive.
+pub trait Cost: Copy + Ord + Debug + Add<Output = Self> {
+    const ZERO: Self;
+}
+
+macro_rules! impl_cost {
+    ($($t:ty),*) => {$(
+        impl Cost for $t {
+            const ZERO: Self = 0;
+        }
+    )*};
+}
+
+impl_cost!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize);
+
+/// How much work a search did.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    /// States taken off the frontier and expanded.
+    pub expanded: usize,
+    /// Distinct states reached, including the start.
+    pub discovered: usize,
+    /// The largest the frontier grew.
+    pub max_frontier: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SearchResult<S, C> {
+    /// The total cost of `path`.
+    pub cost: C,
+    /// Every state from the start to the goal, both included.
+    pub path: Vec<S>,
+    pub stats: SearchStats,
+}
+
+/// The cheapest path from `start` to a state satisfying `is_goal`, where `successors` gives each
+/// state's neighbours along with the cost of moving to them.
+pub fn dijkstra<S, C, I>(
+    start: S,
+    successors: impl FnMut(&S) -> I,
+    is_goal: impl FnMut(&S) -> bool,
+) -> Option<SearchResult<S, C>>
+where
+    S: Clone + Eq + Hash,
+    C: Cost,
+    I: IntoIterator<Item = (S, C)>,
+{
+    astar(start, successors, |_| C::ZERO, is_goal)
+}
+
+/// Like [`dijkstra`], but expands states in order of cost so far plus `heuristic`, an estimate of
+/// the remaining cost. The result is still the cheapest path as long as the heuristic never
+/// overestimates.
+pub fn astar<S, C, I>(
+    start: S,
+    mut successors: impl FnMut(&S) -> I,
+    mut heuristic: impl FnMut(&S) -> C,
+    mut is_goal: impl FnMut(&S) -> bool,
+) -> Option<SearchResult<S, C>>
+where
+    S: Clone + Eq + Hash,
+    C: Cost,
+    I: IntoIterator<Item = (S, C)>,
+{
+    let mut states = States::new(start);
+    let mut best_cost: Vec<C> = vec![C::ZERO];
+    let mut stats = SearchStats::default();
+
+    // the heap holds (estimated total, cost so far, state index), cheapest estimate first
+    let mut frontier = BinaryHeap::new();
+    frontier.push(Reverse((heuristic(&states.states[0]), C::ZERO, 0)));
+
+    while let Some(Reverse((_, cost, index))) = frontier.pop() {
+        if cost > best_cost[index] {
+            // already reached more cheaply
+            continue;
+        }
+
+        stats.expanded += 1;
+        if is_goal(&states.states[index]) {
+            stats.discovered = states.states.len();
+            return Some(SearchResult {
+                cost,
+                path: states.path_to(index),
+                stats,
+            });
+        }
+
+        for (next, step_cost) in successors(&states.states[index]) {
+            let next_cost = cost + step_cost;
+            let (next_index, is_new) = states.index_of(next, index);
+            if is_new {
+                best_cost.push(next_cost);
+            } else if next_cost < best_cost[next_index] {
+                best_cost[next_index] = next_cost;
+                states.parents[next_index] = Some(index);
+            } else {
+                continue;
+            }
+            let estimate = next_cost + heuristic(&states.states[next_index]);
+            frontier.push(Reverse((estimate, next_cost, next_index)));
+        }
+        stats.max_frontier = stats.max_frontier.max(frontier.len());
+    }
+
+    None
+}
+
+/// The path with the fewest steps from `start` to a state satisfying `is_goal`; its cost is the
+/// number of steps.
+pub fn bfs<S, I>(
+    start: S,
+    mut successors: impl FnMut(&S) -> I,
+    mut is_goal: impl FnMut(&S) -> bool,
+) -> Option<SearchResult<S, usize>>
+where
+    S: Clone + Eq + Hash,
+    I: IntoIterator<Item = S>,
+{
+    let mut states = States::new(start);
+    let mut stats = SearchStats::default();
+
+    // (state index, steps from the start)
+    let mut frontier = VecDeque::from([(0, 0)]);
+
+    while let Some((index, steps)) = frontier.pop_front() {
+        stats.expanded += 1;
+        if is_goal(&states.states[index]) {
+            stats.discovered = states.states.len();
+            return Some(SearchResult {
+                cost: steps,
+                path: states.path_to(index),
+                stats,
+            });
+        }
+
+        for next in successors(&states.states[index]) {
+            let (next_index, is_new) = states.index_of(next, index);
+            if is_new {
+                frontier.push_back((next_index, steps + 1));
+            }
+        }
+        stats.max_frontier = stats.max_frontier.max(frontier.len());
+    }
+
+    None
+}
+
+/// Every state seen so far, numbered in the order they were reached, and how each was reached.
+struct States<S> {
+    states: Vec<S>,
+    parents: Vec<Option<usize>>,
+    index_by_state: HashMap<S, usize>,
+}
+
+impl<S: Clone + Eq + Hash> States<S> {
+    fn new(start: S) -> States<S> {
+        States {
+            states: vec![start.clone()],
+            parents: vec![None],
+            index_by_state: HashMap::from([(start, 0)]),
+        }
+    }
+
+    /// The index of `state`, and whether it's new (in which case it was reached from `parent`).
+    fn index_of(&mut self, state: S, parent: usize) -> (usize, bool) {
+        match self.index_by_state.entry(state) {
+            Entry::Occupied(entry) => (*entry.get(), false),
+            Entry::Vacant(entry) => {
+                let index = self.states.len();
+                self.states.push(entry.key().clone());
+                self.parents.push(Some(parent));
+                entry.insert(index);
+                (index, true)
+            }
+        }
+    }
+
+    fn path_to(&self, index: usize) -> Vec<S> {
+        let mut path = vec![self.states[index].clone()];
+        let mut current = index;
+        while let Some(parent) = self.parents[current] {
+            path.push(self.states[parent].clone());
+            current = parent;
+        }
+        path.reverse();
+        path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::search::{astar, bfs, dijkstra};
+
+    /// Moves along a number line: +1 costs 3, +5 costs 10, -1 costs 1.
+    fn number_line(n: &i32) -> Vec<(i32, u32)> {
+        vec![(n + 1, 3), (n + 5, 10), (n - 1, 1)]
+            .into_iter()
+            .filter(|(next, _)| (-10..=30).contains(next))
+            .collect()
+    }
+
+    #[test]
+    fn test_dijkstra_finds_cheapest_path() {
+        let result = dijkstra(0, number_line, |&n| n == 9).unwrap();
+        // e.g. +5 +5 -1 costs 21, cheaper than nine +1 steps (27)
+        assert_eq!(21, result.cost);
+        assert_eq!(4, result.path.len());
+        assert_eq!(
+            (Some(&0), Some(&9)),
+            (result.path.first(), result.path.last())
+        );
+    }
+
+    #[test]
+    fn test_astar_matches_dijkstra_with_less_work() {
+        let goal = 27;
+        let plain = dijkstra(0, number_line, |&n| n == goal).unwrap();
+        // every unit of progress costs at least 2
+        let heuristic = |n: &i32| 2 * (goal - n).max(0) as u32;
+        let guided = astar(0, number_line, heuristic, |&n| n == goal).unwrap();
+
+        assert_eq!(plain.cost, guided.cost);
+        assert!(guided.stats.expanded < plain.stats.expanded);
+    }
+
+    #[test]
+    fn test_bfs_counts_steps() {
+        let result = bfs(
+            (0, 0),
+            |&(x, y): &(i32, i32)| {
+                [(x + 1, y), (x, y + 1)]
+                    .into_iter()
+                    .filter(|p| p.0 < 4 && p.1 < 4)
+            },
+            |&p| p == (3, 2),
+        )
+        .unwrap();
+        assert_eq!(5, result.cost);
+        assert_eq!(6, result.path.len());
+        assert_eq!(Some(&(3, 2)), result.path.last());
+    }
+
+    #[test]
+    fn test_unreachable_goal() {
+        assert_eq!(None, dijkstra(0, number_line, |&n| n == 31));
+        assert_eq!(
+            None,
+            bfs(0, |&n: &i32| (n < 5).then_some(n + 1), |&n| n == 6)
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Step-at-a-time simulations, with helpers to run them and to find when they start repeating.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Something that moves forward one step at a time, like a grid of octopuses or a game.
+pub trait Simulation {
+    /// What each step reports, e.g. how many octopuses flashed.
+    type Output;
+
+    fn step(&mut self) -> Self::Output;
+
+    /// Runs `n` steps, returning what each one reported.
+    fn run_for(&mut self, n: usize) -> Vec<Self::Output> {
+        (0..n).map(|_| self.step()).collect()
+    }
+
+    /// Steps until `done` holds for the new state and the step's output, returning how many steps
+    /// that took. Never returns if `done` never holds.
+    fn run_until(&mut self, mut done: impl FnMut(&Self, &Self::Output) -> bool) -> usize
+    where
+        Self: Sized,
+    {
+        let mut steps = 0;
+        loop {
+            let output = self.step();
+            steps += 1;
+            if done(self, &output) {
+                return steps;
+            }
+        }
+    }
+
+    /// Steps until a state repeats, giving up after `max_steps`. The simulation is left at the
+    /// first repeated state.
+    fn find_cycle(&mut self, max_steps: usize) -> Option<Cycle>
+    where
+        Self: Sized + Clone + Eq + Hash,
+    {
+        let mut first_seen: HashMap<Self, usize> = HashMap::new();
+        for steps in 0..=max_steps {
+            if let Some(&start) = first_seen.get(self) {
+                return Some(Cycle {
+                    start,
+                    period: steps - start,
+                });
+            }
+            first_seen.insert(self.clone(), steps);
+            if steps < max_steps {
+                self.step();
+            }
+        }
+        None
+    }
+}
+
+/// States repeat every `period` steps once `start` steps have been taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub start: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    /// The number of steps, at most `start + period - 1`, that reaches the same state as `steps`.
+    pub fn equivalent_step(&self, steps: usize) -> usize {
+        if steps < self.start {
+            steps
+        } else {
+            self.start + (steps - self.start) % self.period
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::simulation::{Cycle, Simulation};
+
+    /// x -> x² mod 11, starting from 3: 3, 9, 4, 5, 3, ...
+    #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+    struct Squares(u32);
+
+    impl Simulation for Squares {
+        type Output = u32;
+
+        fn step(&mut self) -> u32 {
+            self.0 = self.0 * self.0 % 11;
+            self.0
+        }
+    }
+
+    #[test]
+    fn test_run_for_and_until() {
+        assert_eq!(vec![9, 4, 5, 3, 9], Squares(3).run_for(5));
+
+        let mut squares = Squares(3);
+        assert_eq!(3, squares.run_until(|_, &x| x == 5));
+        assert_eq!(Squares(5), squares);
+    }
+
+    #[test]
+    fn test_find_cycle() {
+        // 2, 4, 5, 3, 9, 4: the cycle is entered after one step
+        let mut squares = Squares(2);
+        let cycle = squares.find_cycle(100).unwrap();
+        assert_eq!(
+            Cycle {
+                start: 1,
+                period: 4
+            },
+            cycle
+        );
+        assert_eq!(Squares(4), squares);
+
+        assert_eq!(3, cycle.equivalent_step(3));
+        assert_eq!(2, cycle.equivalent_step(1_000_002));
+        assert_eq!(None, Squares(2).find_cycle(4));
+    }
+}
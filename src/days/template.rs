@@ -1,4 +1,25 @@
+//! The [`Solution`] trait every day implements.
+
+use crate::days::image::{Image, Palette};
+
 pub trait Solution {
     fn part1(&self, input: &str) -> String;
     fn part2(&self, input: &str) -> String;
+
+    /// The parsed input rendered as a GraphViz DOT graph, for days whose input is a graph.
+    fn to_dot(&self, _input: &str) -> Option<String> {
+        None
+    }
+
+    /// The puzzle drawn as an image, one pixel per grid cell or point, for days that have a
+    /// picture to show. `palette` replaces the day's own colors.
+    fn to_image(&self, _input: &str, _palette: Option<&Palette>) -> Option<Image> {
+        None
+    }
+
+    /// The parsed input as JSON, for days whose puzzle models can be serialized.
+    #[cfg(feature = "serde")]
+    fn to_json(&self, _input: &str) -> Option<serde_json::Value> {
+        None
+    }
 }
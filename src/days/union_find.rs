@@ -0,0 +1,156 @@
+//! Disjoint sets, and the connected components of a grid found with them.
+
+/// Disjoint sets over the elements `0..len`, merged by size with path compression.
+#[derive(Debug, Clone)]
+pub struct UnionFind {
+    parents: Vec<usize>,
+    sizes: Vec<usize>,
+    set_count: usize,
+}
+
+impl UnionFind {
+    /// `len` elements, each in a set of its own.
+    pub fn new(len: usize) -> UnionFind {
+        UnionFind {
+            parents: (0..len).collect(),
+            sizes: vec![1; len],
+            set_count: len,
+        }
+    }
+
+    /// The representative of the set holding `element`.
+    pub fn find(&mut self, element: usize) -> usize {
+        let mut root = element;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        // point everything on the way straight at the root
+        let mut current = element;
+        while self.parents[current] != root {
+            let next = self.parents[current];
+            self.parents[current] = root;
+            current = next;
+        }
+        root
+    }
+
+    /// Merges the sets holding `a` and `b`, returning false if they were already the same set.
+    pub fn union(&mut self, a: usize, b: usize) -> bool {
+        let (root_a, root_b) = (self.find(a), self.find(b));
+        if root_a == root_b {
+            return false;
+        }
+
+        let (larger, smaller) = if self.sizes[root_a] >= self.sizes[root_b] {
+            (root_a, root_b)
+        } else {
+            (root_b, root_a)
+        };
+        self.parents[smaller] = larger;
+        self.sizes[larger] += self.sizes[smaller];
+        self.set_count -= 1;
+        true
+    }
+
+    pub fn same_set(&mut self, a: usize, b: usize) -> bool {
+        self.find(a) == self.find(b)
+    }
+
+    pub fn set_size(&mut self, element: usize) -> usize {
+        let root = self.find(element);
+        self.sizes[root]
+    }
+
+    pub fn set_count(&self) -> usize {
+        self.set_count
+    }
+}
+
+/// The connected components of a grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridComponents {
+    /// Each cell's component, row by row, or `None` for cells outside every component. Components
+    /// are numbered from 0 in the order their first cell appears.
+    pub labels: Vec<Option<usize>>,
+    /// How many cells each component has, indexed by label.
+    pub sizes: Vec<usize>,
+}
+
+/// Groups the cells of a grid `width` wide (given row by row) for which `is_member` holds into
+/// components of orthogonally adjacent cells.
+pub fn label_grid<T>(width: usize, cells: &[T], is_member: impl Fn(&T) -> bool) -> GridComponents {
+    let members: Vec<bool> = cells.iter().map(is_member).collect();
+    let mut sets = UnionFind::new(cells.len());
+    for (index, _) in members.iter().enumerate().filter(|(_, &member)| member) {
+        // joining each cell to the ones right of and below it covers every adjacent pair
+        if (index + 1) % width != 0 && members[index + 1] {
+            sets.union(index, index + 1);
+        }
+        if index + width < cells.len() && members[index + width] {
+            sets.union(index, index + width);
+        }
+    }
+
+    let mut label_by_root: Vec<Option<usize>> = vec![None; cells.len()];
+    let mut sizes = Vec::new();
+    let labels = (0..cells.len())
+        .map(|index| {
+            if !members[index] {
+                return None;
+            }
+            let root = sets.find(index);
+            let label = *label_by_root[root].get_or_insert_with(|| {
+                sizes.push(0);
+                sizes.len() - 1
+            });
+            sizes[label] += 1;
+            Some(label)
+        })
+        .collect();
+
+    GridComponents { labels, sizes }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::days::union_find::{label_grid, UnionFind};
+
+    #[test]
+    fn test_union_find() {
+        let mut sets = UnionFind::new(6);
+        assert!(sets.union(0, 1));
+        assert!(sets.union(2, 3));
+        assert!(sets.union(1, 3));
+        assert!(!sets.union(0, 2));
+
+        assert!(sets.same_set(0, 3));
+        assert!(!sets.same_set(0, 4));
+        assert_eq!(4, sets.set_size(2));
+        assert_eq!(1, sets.set_size(5));
+        assert_eq!(3, sets.set_count());
+    }
+
+    #[test]
+    fn test_label_grid() {
+        // three components of '#', one turning a corner, and no joining across row ends
+        let cells: Vec<char> = "##.\
+                                .#.\
+                                ..#\
+                                #.#"
+        .chars()
+        .collect();
+        let components = label_grid(3, &cells, |&ch| ch == '#');
+
+        assert_eq!(vec![3, 2, 1], components.sizes);
+        let labels = components.labels;
+        assert_eq!(
+            vec![Some(0), Some(0), None, None, Some(0), None],
+            labels[..6].to_vec()
+        );
+        assert_eq!(
+            vec![None, None, Some(1), Some(2), None, Some(1)],
+            labels[6..].to_vec()
+        );
+    }
+}
@@ -0,0 +1,50 @@
+//! Solutions to the [2021 Advent of Code](https://adventofcode.com/2021) puzzles, along with the
+//! grid, graph and search toolkit they're built from.
+//!
+//! Each day is a unit struct implementing [`Solution`], which takes the puzzle input as a string:
+//!
+//! ```
+//! use adventofcode2021::get_day;
+//!
+//! let day01 = get_day(1).unwrap();
+//! assert_eq!("1", day01.part1("199\n200"));
+//! ```
+//!
+//! The reusable pieces live alongside the days in [`days`], e.g. [`days::graph::Graph`],
+//! [`days::search::astar`] and [`days::counter::Counter`].
+
+pub mod days;
+
+pub use days::template::Solution;
+
+/// The days with a solution, in order.
+pub const SUPPORTED_DAYS: [usize; 19] = [
+    1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 21,
+];
+
+/// The solution for `day`, or `None` if that day hasn't been solved.
+pub fn get_day(day: usize) -> Option<Box<dyn Solution>> {
+    let solution: Box<dyn Solution> = match day {
+        1 => Box::new(days::day01::Day01 {}),
+        2 => Box::new(days::day02::Day02 {}),
+        3 => Box::new(days::day03::Day03 {}),
+        4 => Box::new(days::day04::Day04 {}),
+        5 => Box::new(days::day05::Day05 {}),
+        6 => Box::new(days::day06::Day06 {}),
+        7 => Box::new(days::day07::Day07 {}),
+        8 => Box::new(days::day08::Day08 {}),
+        9 => Box::new(days::day09::Day09 {}),
+        10 => Box::new(days::day10::Day10 {}),
+        11 => Box::new(days::day11::Day11 {}),
+        12 => Box::new(days::day12::Day12 {}),
+        13 => Box::new(days::day13::Day13 {}),
+        14 => Box::new(days::day14::Day14 {}),
+        15 => Box::new(days::day15::Day15 {}),
+        16 => Box::new(days::day16::Day16 {}),
+        17 => Box::new(days::day17::Day17 {}),
+        18 => Box::new(days::day18::Day18 {}),
+        21 => Box::new(days::day21::Day21 {}),
+        _ => return None,
+    };
+    Some(solution)
+}
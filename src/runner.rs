@@ -1,8 +1,10 @@
-use crate::days::template::Solution;
-use clap::{App, Arg};
-use std::path::PathBuf;
-
-mod days;
+use adventofcode2021::days::day04::{self, BingoRules};
+use adventofcode2021::days::day05::{self, Point, VentIndex};
+use adventofcode2021::days::image::Palette;
+use adventofcode2021::days::interval::{Cuboid, Interval};
+use adventofcode2021::{get_day, Solution};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
+use std::path::{Path, PathBuf};
 
 fn main() {
     env_logger::init();
@@ -10,50 +12,161 @@ fn main() {
     let app = App::new("AoC Runner")
         .version("1.0")
         .about("Runs AoC 2021 solutions")
+        .arg(day_arg())
+        .arg(
+            Arg::with_name("dot")
+                .long("dot")
+                .value_name("FILE")
+                .help("Writes the day's parsed input graph to FILE in GraphViz DOT format"),
+        )
+        .arg(
+            Arg::with_name("image")
+                .long("image")
+                .value_name("FILE")
+                .help("Draws the day's puzzle to FILE, a .png or .ppm image"),
+        )
+        .arg(
+            Arg::with_name("scale")
+                .long("scale")
+                .value_name("PIXELS")
+                .default_value("4")
+                .validator(is_positive_count)
+                .help("Size in pixels of each grid cell or point drawn with --image"),
+        )
         .arg(
-            Arg::with_name("day")
-                .short("d")
-                .long("day")
-                .required(true)
-                .value_name("DAY_NUMBER")
-                .validator(is_valid_day),
+            Arg::with_name("palette")
+                .long("palette")
+                .value_name("COLORS")
+                .validator(is_palette)
+                .help(
+                    "Colors for --image instead of the day's own, as gradient:RRGGBB,RRGGBB \
+                     or discrete:RRGGBB,RRGGBB,...",
+                ),
+        )
+        .setting(AppSettings::SubcommandsNegateReqs)
+        .subcommand(
+            SubCommand::with_name("bingo-odds")
+                .about("Estimates each day 4 board's odds of winning over shuffled draws")
+                .arg(
+                    Arg::with_name("trials")
+                        .long("trials")
+                        .value_name("GAMES")
+                        .default_value("10000")
+                        .validator(is_positive_count)
+                        .help("How many games to play"),
+                )
+                .arg(
+                    Arg::with_name("seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .default_value("2021")
+                        .validator(is_valid_seed)
+                        .help("Seeds the shuffles, so the same seed gives the same odds"),
+                )
+                .arg(
+                    Arg::with_name("diagonals")
+                        .long("diagonals")
+                        .help("Lets diagonals win as well as rows and columns"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("vents")
+                .about("Answers questions about where the day 5 vent lines are")
+                .arg(
+                    Arg::with_name("point")
+                        .long("point")
+                        .value_name("X,Y")
+                        .validator(|val| is_coordinate_list(val, 2))
+                        .help("Lists the lines covering the point"),
+                )
+                .arg(
+                    Arg::with_name("area")
+                        .long("area")
+                        .value_name("X1,Y1,X2,Y2")
+                        .validator(|val| is_coordinate_list(val, 4))
+                        .help("Counts the points covered by two or more lines inside the corners"),
+                )
+                .arg(
+                    Arg::with_name("pairs")
+                        .long("pairs")
+                        .help("Lists every pair of lines that meet"),
+                ),
         );
 
+    #[cfg(feature = "serde")]
+    let app = app.subcommand(
+        SubCommand::with_name("dump")
+            .about("Prints the day's parsed input as JSON")
+            .arg(day_arg()),
+    );
+
     let matches = app.get_matches();
 
-    let day_int = matches
-        .value_of("day")
-        .map(|s| s.parse::<usize>().unwrap())
-        .unwrap();
+    if let Some(odds_matches) = matches.subcommand_matches("bingo-odds") {
+        print_bingo_odds(odds_matches);
+        return;
+    }
+
+    if let Some(vents_matches) = matches.subcommand_matches("vents") {
+        print_vent_queries(vents_matches);
+        return;
+    }
+
+    #[cfg(feature = "serde")]
+    if let Some(dump_matches) = matches.subcommand_matches("dump") {
+        let day_int = day_value(dump_matches);
+        dump_day(
+            day_int,
+            get_day_impl(day_int).as_ref(),
+            &read_day_input(&day_int),
+        );
+        return;
+    }
+
+    let day_int = day_value(&matches);
     let solution = get_day_impl(day_int);
 
     let input = read_day_input(&day_int);
+    if let Some(dot_path) = matches.value_of("dot") {
+        write_day_dot(day_int, solution.as_ref(), &input, dot_path);
+    }
+    if let Some(image_path) = matches.value_of("image") {
+        let scale = matches.value_of("scale").unwrap().parse().unwrap();
+        let palette: Option<Palette> = matches.value_of("palette").map(|p| p.parse().unwrap());
+        write_day_image(
+            day_int,
+            solution.as_ref(),
+            &input,
+            image_path,
+            scale,
+            palette.as_ref(),
+        );
+    }
+
     println!("[{},1]: {}", day_int, solution.part1(&input));
     println!("[{},2]: {}", day_int, solution.part2(&input));
 }
 
+fn day_arg() -> Arg<'static, 'static> {
+    Arg::with_name("day")
+        .short("d")
+        .long("day")
+        .required(true)
+        .value_name("DAY_NUMBER")
+        .validator(is_valid_day)
+}
+
+fn day_value(matches: &ArgMatches) -> usize {
+    matches
+        .value_of("day")
+        .map(|s| s.parse::<usize>().unwrap())
+        .unwrap()
+}
+
 fn get_day_impl(day_int: usize) -> Box<dyn Solution> {
-    match day_int {
-        1 => Box::new(days::day01::Day01 {}),
-        2 => Box::new(days::day02::Day02 {}),
-        3 => Box::new(days::day03::Day03 {}),
-        4 => Box::new(days::day04::Day04 {}),
-        5 => Box::new(days::day05::Day05 {}),
-        6 => Box::new(days::day06::Day06 {}),
-        7 => Box::new(days::day07::Day07 {}),
-        8 => Box::new(days::day08::Day08 {}),
-        9 => Box::new(days::day09::Day09 {}),
-        10 => Box::new(days::day10::Day10 {}),
-        11 => Box::new(days::day11::Day11 {}),
-        12 => Box::new(days::day12::Day12 {}),
-        13 => Box::new(days::day13::Day13 {}),
-        14 => Box::new(days::day14::Day14 {}),
-        15 => Box::new(days::day15::Day15 {}),
-        16 => Box::new(days::day16::Day16 {}),
-        17 => Box::new(days::day17::Day17 {}),
-        18 => Box::new(days::day18::Day18 {}),
-        21 => Box::new(days::day21::Day21 {}),
-        d => panic!("Day {} not yet supported", d),
+    match get_day(day_int) {
+        Some(solution) => solution,
+        None => panic!("Day {} not yet supported", day_int),
     }
 }
 
@@ -70,6 +183,121 @@ fn read_day_input(day_int: &usize) -> String {
     }
 }
 
+fn write_day_dot(day_int: usize, solution: &dyn Solution, input: &str, dot_path: &str) {
+    let dot = match solution.to_dot(input) {
+        Some(dot) => dot,
+        None => panic!("Day {} has no graph to export", day_int),
+    };
+    if let Err(e) = std::fs::write(dot_path, dot) {
+        panic!("{}", e)
+    }
+}
+
+fn write_day_image(
+    day_int: usize,
+    solution: &dyn Solution,
+    input: &str,
+    image_path: &str,
+    scale: usize,
+    palette: Option<&Palette>,
+) {
+    let image = match solution.to_image(input, palette) {
+        Some(image) => image,
+        None => panic!("Day {} has no image to draw", day_int),
+    };
+    if let Err(e) = image.scaled(scale).save(Path::new(image_path)) {
+        panic!("{}", e)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn dump_day(day_int: usize, solution: &dyn Solution, input: &str) {
+    let json = match solution.to_json(input) {
+        Some(json) => json,
+        None => panic!("Day {} has nothing to dump", day_int),
+    };
+    match serde_json::to_string_pretty(&json) {
+        Ok(pretty) => println!("{}", pretty),
+        Err(e) => panic!("{}", e),
+    }
+}
+
+fn print_bingo_odds(matches: &ArgMatches) {
+    let trials = matches.value_of("trials").unwrap().parse().unwrap();
+    let seed = matches.value_of("seed").unwrap().parse().unwrap();
+    let rules = BingoRules {
+        diagonals: matches.is_present("diagonals"),
+    };
+
+    let (numbers, boards) = day04::parse_input(&read_day_input(&4)).expect("Invalid input");
+    println!("board  first   last    wins    avg turn");
+    for odds in day04::estimate_odds(&numbers, &boards, rules, trials, seed) {
+        let turn = odds
+            .expected_turn
+            .map_or(String::from("-"), |turn| format!("{:.1}", turn));
+        println!(
+            "{:<6} {:<7.4} {:<7.4} {:<7.4} {}",
+            odds.board, odds.first, odds.last, odds.wins, turn
+        );
+    }
+}
+
+fn print_vent_queries(matches: &ArgMatches) {
+    let segments = day05::Day05::parse_input(&read_day_input(&5)).expect("Invalid input");
+    let index = VentIndex::new(segments);
+
+    if let Some(point) = matches.value_of("point") {
+        let (x, y) = match coordinates(point)[..] {
+            [x, y] => (x, y),
+            _ => unreachable!(),
+        };
+        let point = Point::new(x, y);
+        let covering = index.covering(point);
+        println!("{} line(s) cover {}", covering.len(), point);
+        for i in covering {
+            println!("  {}", index.segments()[i]);
+        }
+    }
+    if let Some(area) = matches.value_of("area") {
+        let (x1, y1, x2, y2) = match coordinates(area)[..] {
+            [x1, y1, x2, y2] => (x1, y1, x2, y2),
+            _ => unreachable!(),
+        };
+        let area = Cuboid::new([
+            Interval::new(x1.min(x2), x1.max(x2)).unwrap(),
+            Interval::new(y1.min(y2), y1.max(y2)).unwrap(),
+        ]);
+        println!(
+            "{} overlapping point(s) from {},{} to {},{}",
+            index.overlap_count_in(&area),
+            x1,
+            y1,
+            x2,
+            y2
+        );
+    }
+    if matches.is_present("pairs") {
+        let pairs = index.intersecting_pairs();
+        println!("{} pair(s) of lines meet", pairs.len());
+        for (a, b) in pairs {
+            println!("  {}  and  {}", index.segments()[a], index.segments()[b]);
+        }
+    }
+}
+
+fn coordinates(val: &str) -> Vec<i64> {
+    val.split(',').map(|n| n.trim().parse().unwrap()).collect()
+}
+
+fn is_coordinate_list(val: String, count: usize) -> Result<(), String> {
+    let parsed: Result<Vec<i64>, _> = val.split(',').map(|n| n.trim().parse::<i64>()).collect();
+    match parsed {
+        Ok(values) if values.len() == count => Ok(()),
+        Ok(_) => Err(format!("expected {} comma-separated integers", count)),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
 fn is_valid_day(val: String) -> Result<(), String> {
     match val.parse::<usize>() {
         Ok(int_val) => {
@@ -82,3 +310,19 @@ fn is_valid_day(val: String) -> Result<(), String> {
         Err(e) => Err(e.to_string()),
     }
 }
+
+fn is_valid_seed(val: String) -> Result<(), String> {
+    val.parse::<u64>().map(|_| ()).map_err(|e| e.to_string())
+}
+
+fn is_palette(val: String) -> Result<(), String> {
+    val.parse::<Palette>().map(|_| ())
+}
+
+fn is_positive_count(val: String) -> Result<(), String> {
+    match val.parse::<usize>() {
+        Ok(0) => Err(String::from("must be at least 1")),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
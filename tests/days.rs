@@ -0,0 +1,59 @@
+use adventofcode2021::days::day15::Day15;
+use adventofcode2021::{get_day, Solution, SUPPORTED_DAYS};
+use std::path::PathBuf;
+
+fn read_input(day: usize) -> String {
+    let path = PathBuf::from(format!(
+        "{}/inputs/day{:02}.txt",
+        env!("CARGO_MANIFEST_DIR"),
+        day
+    ));
+    std::fs::read_to_string(path).unwrap()
+}
+
+#[test]
+fn test_every_supported_day_is_found() {
+    for day in SUPPORTED_DAYS {
+        assert!(get_day(day).is_some(), "day {}", day);
+    }
+    assert!(get_day(19).is_none());
+    assert!(get_day(0).is_none());
+}
+
+#[test]
+fn test_sample_through_lookup() {
+    let day02 = get_day(2).unwrap();
+    let sample = "forward 5\ndown 5\nforward 8\nup 3\ndown 8\nforward 2";
+    assert_eq!("150", day02.part1(sample));
+    assert_eq!("900", day02.part2(sample));
+}
+
+#[test]
+fn test_real_inputs() {
+    let expected = [
+        (1, "1195", "1235"),
+        (6, "360761", "1632779838045"),
+        (14, "3587", "3906445077999"),
+    ];
+    for (day, part1, part2) in expected {
+        let solution = get_day(day).unwrap();
+        let input = read_input(day);
+        assert_eq!(part1, solution.part1(&input), "day {} part 1", day);
+        assert_eq!(part2, solution.part2(&input), "day {} part 2", day);
+    }
+}
+
+#[test]
+fn test_day_used_directly() {
+    let sample = "1163751742
+1381373672
+2136511328
+3694931569
+7463417111
+1319128137
+1359912421
+3125421639
+1293138521
+2311944581";
+    assert_eq!("40", Day15 {}.part1(sample));
+}
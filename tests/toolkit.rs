@@ -0,0 +1,41 @@
+use adventofcode2021::days::counter::Counter;
+use adventofcode2021::days::graph::Graph;
+use adventofcode2021::days::interval::{Interval, IntervalSet};
+use adventofcode2021::days::search::bfs;
+use adventofcode2021::days::union_find::label_grid;
+
+#[test]
+fn test_bfs_over_a_parsed_graph() {
+    let graph: Graph =
+        Graph::from_edge_list("start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end").unwrap();
+    let start = graph.node_index("start").unwrap();
+    let end = graph.node_index("end").unwrap();
+
+    let result = bfs(start, |&node| graph.successors(node), |&node| node == end).unwrap();
+    assert_eq!(2, result.cost);
+    assert_eq!(Some(&end), result.path.last());
+}
+
+#[test]
+fn test_counter() {
+    let counter: Counter<char> = "NNCB".chars().collect();
+    assert_eq!(2, counter.get(&'N'));
+    assert_eq!(Some((&'N', 2)), counter.most_common());
+}
+
+#[test]
+fn test_interval_set() {
+    let set: IntervalSet<i32> = [(1, 3), (2, 6), (10, 12)]
+        .into_iter()
+        .map(|(start, end)| Interval::new(start, end).unwrap())
+        .collect();
+    assert_eq!(2, set.intervals().len());
+    assert_eq!(9, set.len());
+}
+
+#[test]
+fn test_label_grid() {
+    let heights = [2, 1, 9, 9, 3, 9, 8, 7];
+    let components = label_grid(4, &heights, |&h| h < 9);
+    assert_eq!(vec![3, 2], components.sizes);
+}